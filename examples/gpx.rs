@@ -150,6 +150,7 @@ struct Person {
     link: Option<Link>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 struct Pt {
     #[serde(rename = "@lat")]
@@ -161,6 +162,7 @@ struct Pt {
     time: Option<XsdDateTime>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 struct PtSeg {
     pt: Vec<Pt>,
@@ -201,6 +203,7 @@ enum Fix {
     Pps,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 struct DgpsStation(u16);
 
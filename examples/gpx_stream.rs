@@ -0,0 +1,53 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io::BufReader;
+
+use xserde::Deserializer;
+
+#[derive(Deserialize)]
+struct TrkPt {
+    #[serde(rename = "@lat")]
+    lat: f64,
+    #[serde(rename = "@lon")]
+    lon: f64,
+    ele: Option<f64>,
+}
+
+/// Processes all track points of a GPX file without loading the whole file
+fn main() {
+    let arg = env::args().nth(1).expect("missing gpx argument");
+
+    let reader = BufReader::new(fs::File::open(arg).unwrap());
+    let mut de = Deserializer::from_reader(reader);
+
+    let mut count = 0usize;
+    let mut max_ele: Option<f64> = None;
+    let (mut min_lat, mut max_lat) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut min_lon, mut max_lon) = (f64::INFINITY, f64::NEG_INFINITY);
+    for point in de.iter_path::<TrkPt>("gpx/trk/trkseg/trkpt") {
+        let point = match point {
+            Err(err) => {
+                println!("error: {}", err);
+                return;
+            }
+            Ok(point) => point,
+        };
+        count += 1;
+        min_lat = min_lat.min(point.lat);
+        max_lat = max_lat.max(point.lat);
+        min_lon = min_lon.min(point.lon);
+        max_lon = max_lon.max(point.lon);
+        if let Some(ele) = point.ele {
+            max_ele = Some(max_ele.map_or(ele, |max| max.max(ele)));
+        }
+    }
+
+    println!("Points: {}", count);
+    if count > 0 {
+        println!("Bounds: {} {} - {} {}", min_lat, min_lon, max_lat, max_lon);
+    }
+    if let Some(ele) = max_ele {
+        println!("Maximal elevation: {}", ele);
+    }
+}
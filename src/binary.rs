@@ -0,0 +1,187 @@
+//! Text encodings for binary data
+
+use crate::error::Reason;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const INVALID: u8 = 0xff;
+/// Value of each byte as base64 character or `INVALID`
+const BASE64_VALUES: [u8; 256] = base64_values();
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encoding of bytes in element content and attribute values
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BinaryEncoding {
+    /// Base64 with padding like `xs:base64Binary`
+    Base64,
+    /// Hexadecimal digits like `xs:hexBinary`, written in lower case
+    Hex,
+}
+
+impl Default for BinaryEncoding {
+    fn default() -> Self {
+        BinaryEncoding::Base64
+    }
+}
+
+impl BinaryEncoding {
+    pub(crate) fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Base64 => encode_base64(bytes),
+            BinaryEncoding::Hex => encode_hex(bytes),
+        }
+    }
+
+    /// Decodes `text`, whitespace is ignored
+    pub(crate) fn decode(self, text: &[u8]) -> Result<Vec<u8>, Reason> {
+        let result = match self {
+            BinaryEncoding::Base64 => decode_base64(text),
+            BinaryEncoding::Hex => decode_hex(text),
+        };
+        result
+            .ok_or_else(|| Reason::InvalidBinary(self, String::from_utf8_lossy(text).into_owned()))
+    }
+}
+
+const fn base64_values() -> [u8; 256] {
+    let mut values = [INVALID; 256];
+    let mut i = 0;
+    while i < BASE64_ALPHABET.len() {
+        values[BASE64_ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+    values
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len() / 4 * 3);
+    let mut quad = [0u8; 4];
+    let mut len = 0;
+    let mut padded = false;
+    for &c in text.iter().filter(|c| !c.is_ascii_whitespace()) {
+        // padding is only allowed in the last quad
+        if padded {
+            return None;
+        }
+        quad[len] = c;
+        len += 1;
+        if len < 4 {
+            continue;
+        }
+        len = 0;
+
+        let padding = quad.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        padded = padding > 0;
+        let mut n = 0u32;
+        for &c in &quad[..4 - padding] {
+            let value = BASE64_VALUES[c as usize];
+            if value == INVALID {
+                return None;
+            }
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding;
+        // unused bits of the last character must be zeros
+        if n & ((1 << (8 * padding)) - 1) != 0 {
+            return None;
+        }
+        result.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8][..3 - padding]);
+    }
+    if len != 0 {
+        return None;
+    }
+    Some(result)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        result.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        result.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+    result
+}
+
+fn decode_hex(text: &[u8]) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .iter()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|&c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    Some(digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        let cases: &[(&[u8], &str)] = &[
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\x00\xfe", "/wD+"),
+        ];
+        for (bytes, text) in cases {
+            assert_eq!(BinaryEncoding::Base64.encode(bytes), *text);
+            assert_eq!(
+                BinaryEncoding::Base64
+                    .decode(text.as_bytes())
+                    .ok()
+                    .as_deref(),
+                Some(*bytes)
+            );
+        }
+
+        assert_eq!(
+            BinaryEncoding::Base64.decode(b" Zm9v\n  Yg== ").ok(),
+            Some(b"foob".to_vec())
+        );
+        assert!(BinaryEncoding::Base64.decode(b"Zm9v!").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zg=a").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zh==").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zg").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zg=").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zm9").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Z===").is_err());
+        assert!(BinaryEncoding::Base64.decode(b"Zg==Zg==").is_err());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(BinaryEncoding::Hex.encode(b"\x01\xab\xff"), "01abff");
+        assert_eq!(
+            BinaryEncoding::Hex.decode(b"01ABff").ok(),
+            Some(b"\x01\xab\xff".to_vec())
+        );
+        assert!(BinaryEncoding::Hex.decode(b"abc").is_err());
+        assert!(BinaryEncoding::Hex.decode(b"xy").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Literals for booleans
+
+/// Literals for booleans in element content and attribute values
+///
+/// The same setting can be used for the [`Serializer`](crate::Serializer) and the
+/// [`Deserializer`](crate::Deserializer), so that written documents can be read again.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Booleans {
+    /// Read `true`, `1`, `yes` and `false`, `0`, `no`, write `true` and `false`
+    Lenient,
+    /// Read only the literals of `xs:boolean`: `true`, `1`, `false` and `0`, write `true` and
+    /// `false`
+    Strict,
+    /// Read and write only the literals for `true` and `false` in this order, like `Y` and `N`
+    Custom(String, String),
+}
+
+impl Default for Booleans {
+    fn default() -> Self {
+        Booleans::Lenient
+    }
+}
+
+impl Booleans {
+    /// Only the literals `true_literal` and `false_literal`
+    pub fn custom<T: Into<String>, F: Into<String>>(true_literal: T, false_literal: F) -> Self {
+        Booleans::Custom(true_literal.into(), false_literal.into())
+    }
+
+    /// Boolean of `text`, `None` for other literals
+    pub(crate) fn parse(&self, text: &[u8]) -> Option<bool> {
+        match (self, text) {
+            (Booleans::Custom(true_literal, _), _) if text == true_literal.as_bytes() => Some(true),
+            (Booleans::Custom(_, false_literal), _) if text == false_literal.as_bytes() => {
+                Some(false)
+            }
+            (Booleans::Custom(..), _) => None,
+            (_, b"true" | b"1") => Some(true),
+            (_, b"false" | b"0") => Some(false),
+            (Booleans::Lenient, b"yes") => Some(true),
+            (Booleans::Lenient, b"no") => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Literal written for `value`
+    pub(crate) fn literal(&self, value: bool) -> &str {
+        match (self, value) {
+            (Booleans::Custom(true_literal, _), true) => true_literal,
+            (Booleans::Custom(_, false_literal), false) => false_literal,
+            (_, true) => "true",
+            (_, false) => "false",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let literals: &[&[u8]] = &[b"true", b"1", b"yes", b"false", b"0", b"no", b"Y", b"N"];
+        let parse = |booleans: Booleans| {
+            literals
+                .iter()
+                .map(|literal| booleans.parse(literal))
+                .collect::<Vec<_>>()
+        };
+        let (t, f) = (Some(true), Some(false));
+        assert_eq!(parse(Booleans::Lenient), [t, t, t, f, f, f, None, None]);
+        assert_eq!(
+            parse(Booleans::Strict),
+            [t, t, None, f, f, None, None, None]
+        );
+        assert_eq!(
+            parse(Booleans::custom("Y", "N")),
+            [None, None, None, None, None, None, t, f]
+        );
+    }
+}
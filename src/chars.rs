@@ -0,0 +1,268 @@
+//! Character handling, which differs between XML versions
+
+use std::borrow::Cow;
+
+use quick_xml::escape::escape as escape_markup;
+
+use crate::XmlVersion;
+
+/// Handling of characters, which are not allowed in XML documents, when serializing text
+///
+/// These are `\0`, U+FFFE, U+FFFF and in XML 1.0 control characters other than tab and line
+/// breaks. XML 1.1 allows control characters as character references.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InvalidChars {
+    /// Write them unchanged, which produces a malformed document
+    Keep,
+    /// Fail with [`ErrorKind::InvalidChar`](crate::ErrorKind::InvalidChar)
+    Error,
+    /// Leave them out
+    Remove,
+    /// Write the given character instead
+    Replace(char),
+}
+
+impl Default for InvalidChars {
+    fn default() -> Self {
+        InvalidChars::Keep
+    }
+}
+
+/// Handling of map keys, which are no valid XML names, when serializing
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InvalidNames {
+    /// Write them unchanged, which produces a malformed document
+    Keep,
+    /// Fail with [`ErrorKind::InvalidName`](crate::ErrorKind::InvalidName)
+    Error,
+    /// Replace invalid characters with `_` and prepend `_` to names, which start with a digit,
+    /// `-` or `.`
+    Sanitize,
+    /// Write the entry as `<entry key="...">value</entry>`
+    Entry,
+}
+
+impl Default for InvalidNames {
+    fn default() -> Self {
+        InvalidNames::Keep
+    }
+}
+
+fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}'
+        | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}'
+        | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}'
+        )
+}
+
+/// `name` matches the `Name` production of XML
+pub(crate) fn is_name(name: &str) -> bool {
+    name.starts_with(is_name_start_char) && name.chars().all(is_name_char)
+}
+
+/// Valid name similar to `name`, see [`InvalidNames::Sanitize`]
+pub(crate) fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if is_name_char(c) { c } else { '_' })
+        .collect();
+    if !sanitized.starts_with(is_name_start_char) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Character, which can be part of a document directly or as character reference
+fn is_legal(c: char, version: XmlVersion) -> bool {
+    match c {
+        '\t' | '\n' | '\r' => true,
+        '\u{0}' | '\u{FFFE}' | '\u{FFFF}' => false,
+        '\u{1}'..='\u{1F}' => version == XmlVersion::v1_1,
+        _ => true,
+    }
+}
+
+/// Character, which XML 1.1 only allows as character reference
+fn is_restricted(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1}'..='\u{8}'
+            | '\u{B}'
+            | '\u{C}'
+            | '\u{E}'..='\u{1F}'
+            | '\u{7F}'..='\u{84}'
+            | '\u{86}'..='\u{9F}'
+    )
+}
+
+/// Character, which is written as character reference in XML 1.1
+///
+/// NEL and LS are line endings in XML 1.1 and would be read as `\n` otherwise.
+fn needs_reference(c: char) -> bool {
+    is_restricted(c) || c == '\u{85}' || c == '\u{2028}'
+}
+
+/// Escapes `text` for element content and attribute values
+///
+/// Fails with the first character, which is not allowed, for [`InvalidChars::Error`].
+pub(crate) fn escape(
+    text: &str,
+    version: XmlVersion,
+    invalid: InvalidChars,
+) -> Result<Cow<'_, str>, char> {
+    let illegal = |c: char| !is_legal(c, version);
+    if invalid == InvalidChars::Keep || !text.contains(illegal) {
+        return Ok(escape_legal(text, version));
+    }
+
+    let legal: String = match invalid {
+        InvalidChars::Keep => unreachable!(),
+        InvalidChars::Error => return Err(text.chars().find(|&c| illegal(c)).unwrap()),
+        InvalidChars::Remove => text.chars().filter(|&c| !illegal(c)).collect(),
+        InvalidChars::Replace(replacement) => text
+            .chars()
+            .map(|c| if illegal(c) { replacement } else { c })
+            .collect(),
+    };
+    Ok(Cow::Owned(escape_legal(&legal, version).into_owned()))
+}
+
+/// Escapes `text`, which only contains legal characters
+fn escape_legal(text: &str, version: XmlVersion) -> Cow<'_, str> {
+    let escaped = match escape_markup(text.as_bytes()) {
+        // only ASCII characters are replaced
+        Cow::Borrowed(_) => Cow::Borrowed(text),
+        Cow::Owned(escaped) => Cow::Owned(String::from_utf8(escaped).unwrap()),
+    };
+    if version == XmlVersion::v1_0 || !escaped.contains(needs_reference) {
+        return escaped;
+    }
+
+    let mut result = String::with_capacity(escaped.len() + 8);
+    for c in escaped.chars() {
+        if needs_reference(c) {
+            result.push_str(&format!("&#x{:X};", c as u32));
+        } else {
+            result.push(c);
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Replaces the line endings of XML 1.1 (`\r\n`, `\r`, NEL, `\r` NEL and LS) with `\n`
+pub(crate) fn normalize_line_endings(text: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+    let is_line_ending = |(i, &b): (usize, &u8)| {
+        b == b'\r' || text[i..].starts_with(b"\xC2\x85") || text[i..].starts_with(b"\xE2\x80\xA8")
+    };
+    if !text.iter().enumerate().any(is_line_ending) {
+        return text;
+    }
+
+    let mut result = Vec::with_capacity(text.len());
+    let mut rest = &text[..];
+    while let Some(&b) = rest.first() {
+        let line_ending = if rest.starts_with(b"\r\n") || rest.starts_with(b"\xC2\x85") {
+            2
+        } else if rest.starts_with(b"\r\xC2\x85") || rest.starts_with(b"\xE2\x80\xA8") {
+            3
+        } else if b == b'\r' {
+            1
+        } else {
+            result.push(b);
+            rest = &rest[1..];
+            continue;
+        };
+        result.push(b'\n');
+        rest = &rest[line_ending..];
+    }
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_control_characters() {
+        let text = "a<\u{1}\t\u{85}\u{2028}\u{9F}é";
+        let escape = |text, version| escape(text, version, InvalidChars::Keep).unwrap();
+        assert_eq!(
+            escape(text, XmlVersion::v1_0),
+            "a&lt;\u{1}\t\u{85}\u{2028}\u{9F}é"
+        );
+        assert_eq!(
+            escape(text, XmlVersion::v1_1),
+            "a&lt;&#x1;\t&#x85;&#x2028;&#x9F;é"
+        );
+        assert!(matches!(
+            escape("plain", XmlVersion::v1_1),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn invalid_characters() {
+        let text = "a\u{0}<\u{1}\u{FFFE}";
+        let v1_0 = |invalid| escape(text, XmlVersion::v1_0, invalid);
+        assert_eq!(v1_0(InvalidChars::Error), Err('\u{0}'));
+        assert_eq!(v1_0(InvalidChars::Remove).unwrap(), "a&lt;");
+        assert_eq!(
+            v1_0(InvalidChars::Replace('\u{FFFD}')).unwrap(),
+            "a\u{FFFD}&lt;\u{FFFD}\u{FFFD}"
+        );
+        assert_eq!(
+            escape(text, XmlVersion::v1_1, InvalidChars::Remove).unwrap(),
+            "a&lt;&#x1;"
+        );
+        assert_eq!(
+            escape("a\u{1}", XmlVersion::v1_1, InvalidChars::Error).unwrap(),
+            "a&#x1;"
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert!(is_name("soap:Body"));
+        assert!(is_name("_1.a-b·ü"));
+        assert!(!is_name(""));
+        assert!(!is_name("1st"));
+        assert!(!is_name("a b"));
+        assert!(!is_name("a<b"));
+
+        assert_eq!(sanitize_name("1st item"), "_1st_item");
+        assert_eq!(sanitize_name("a<b"), "a_b");
+        assert_eq!(sanitize_name(" a"), "_a");
+        assert_eq!(sanitize_name(""), "_");
+    }
+
+    #[test]
+    fn line_endings() {
+        let normalize = |text: &[u8]| normalize_line_endings(Cow::Borrowed(text)).into_owned();
+        assert_eq!(
+            normalize("a\r\nb\rc\u{85}d\r\u{85}e\u{2028}f\n".as_bytes()),
+            b"a\nb\nc\nd\ne\nf\n"
+        );
+        assert!(matches!(
+            normalize_line_endings(Cow::Borrowed("a\nü".as_bytes())),
+            Cow::Borrowed(_)
+        ));
+    }
+}
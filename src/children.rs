@@ -0,0 +1,182 @@
+//! Sequences of enums as flattened child elements
+//!
+//! Serde reads a structure with flattened fields as a map, so a `$value` field cannot tell the
+//! other fields from the child elements it should take. Use this module with
+//! `#[serde(flatten, with = "xserde::children")]` on a `Vec` of enums instead. The field takes
+//! all child elements, which are no other fields, as enum variants named like the elements, and
+//! text as the variant `$text`. Attributes are skipped.
+//!
+//! The field has to come after the other flattened fields, because serde passes it all elements,
+//! which were not taken before. As serde reads flattened fields untyped, elements with only text
+//! become strings, so use `String` for the content of variants.
+//!
+//! # Examples
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! struct Meta {
+//!     author: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! #[serde(rename_all = "lowercase")]
+//! enum Step {
+//!     Checkout,
+//!     Run(String),
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! struct Job {
+//!     name: String,
+//!     #[serde(flatten)]
+//!     meta: Meta,
+//!     #[serde(flatten, with = "xserde::children")]
+//!     steps: Vec<Step>,
+//! }
+//!
+//! let xml = "<Job><name>ci</name><author>me</author><checkout/><run>tests</run></Job>";
+//! let job: Job = xserde::from_str(xml).unwrap();
+//! assert_eq!(job.steps, vec![Step::Checkout, Step::Run("tests".into())]);
+//!
+//! // structures with flattened fields are written as maps, which need a root name
+//! let mut buffer = Vec::new();
+//! let mut writer = xserde::Writer::new(&mut buffer);
+//! let mut ser = xserde::Serializer::new_with_root(&mut writer, Some("Job")).with_xmldecl(false);
+//! job.serialize(&mut ser).unwrap();
+//! assert_eq!(String::from_utf8(buffer).unwrap(), xml);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, IgnoredAny, IntoDeserializer};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::de::{INNER_VALUE, TEXT};
+
+/// Serializes `children` as child elements
+pub fn serialize<T: Serialize, S: Serializer>(
+    children: &[T],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry(INNER_VALUE, children)?;
+    map.end()
+}
+
+/// Deserializes child elements and text as enum variants
+pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    deserializer.deserialize_map(ChildrenVisitor(PhantomData))
+}
+
+struct ChildrenVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> de::Visitor<'de> for ChildrenVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("child elements")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Vec<T>, A::Error> {
+        let mut children = Vec::new();
+        while let Some(name) = map.next_key::<String>()? {
+            if name.starts_with('@') {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            let variant = if name == INNER_VALUE {
+                TEXT.to_string()
+            } else {
+                name
+            };
+            children.push(T::deserialize(Child {
+                variant,
+                map: &mut map,
+            })?);
+        }
+        Ok(children)
+    }
+}
+
+/// A child element as enum variant, which is named like the element
+struct Child<'a, A> {
+    variant: String,
+    map: &'a mut A,
+}
+
+impl<'de, 'a, A: de::MapAccess<'de>> Deserializer<'de> for Child<'a, A> {
+    type Error = A::Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, A::Error> {
+        visitor.visit_enum(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+impl<'de, 'a, A: de::MapAccess<'de>> de::EnumAccess<'de> for Child<'a, A> {
+    type Error = A::Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), A::Error> {
+        let variant = seed.deserialize(self.variant.as_str().into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, A: de::MapAccess<'de>> de::VariantAccess<'de> for Child<'a, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.map.next_value::<IgnoredAny>()?;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(Content::Tuple(len, visitor))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(Content::Struct(fields, visitor))
+    }
+}
+
+/// Content of a tuple or struct variant
+enum Content<V> {
+    Tuple(usize, V),
+    Struct(&'static [&'static str], V),
+}
+
+impl<'de, V: de::Visitor<'de>> DeserializeSeed<'de> for Content<V> {
+    type Value = V::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        match self {
+            Content::Tuple(len, visitor) => deserializer.deserialize_tuple(len, visitor),
+            Content::Struct(fields, visitor) => {
+                deserializer.deserialize_struct("", fields, visitor)
+            }
+        }
+    }
+}
@@ -3,11 +3,13 @@
 use std::borrow::Cow;
 use std::str::from_utf8;
 
-use quick_xml::escape::unescape;
+use std::rc::Rc;
+
 use quick_xml::Error as XmlError;
 use serde::de::{self, Visitor};
 use serde::{self, forward_to_deserialize_any};
 
+use crate::de::DeserializerOptions;
 use crate::{error::Reason, error::ResultExt, Error, Result};
 
 /// A deserializer for a xml escaped and encoded value
@@ -18,36 +20,62 @@ use crate::{error::Reason, error::ResultExt, Error, Result};
 /// when converting to float, we don't expect any escapable character
 /// anyway
 #[derive(Clone)]
-pub(crate) struct EscapedDeserializer {
+pub(crate) struct EscapedDeserializer<'de> {
     /// Possible escaped value of text/CDATA or attribute value
-    escaped_value: Vec<u8>,
+    escaped_value: Cow<'de, [u8]>,
     /// If `true`, value requires unescaping before using
     escaped: bool,
+    /// Offset of the value in the input
+    offset: usize,
+    options: Rc<DeserializerOptions>,
 }
 
-impl EscapedDeserializer {
-    pub fn new(escaped_value: Vec<u8>, escaped: bool) -> Self {
+impl<'de> EscapedDeserializer<'de> {
+    pub fn new(
+        escaped_value: Cow<'de, [u8]>,
+        escaped: bool,
+        offset: usize,
+        options: Rc<DeserializerOptions>,
+    ) -> Self {
         EscapedDeserializer {
             escaped_value,
             escaped,
+            offset,
+            options,
         }
     }
 
-    fn unescaped(&self) -> Result<Cow<[u8]>> {
+    fn unescaped(&self) -> Result<Cow<'_, [u8]>> {
         if self.escaped {
-            unescape(&self.escaped_value)
-                .map_err(|e| self.error(Reason::Xml(XmlError::EscapeError(e))))
+            self.options
+                .unescape(&self.escaped_value)
+                .map_err(|e| self.error(Reason::Xml(e)))
         } else {
             Ok(Cow::Borrowed(&self.escaped_value))
         }
     }
 
+    /// Unescaped value, which is still borrowed from the input if possible
+    fn into_unescaped(self) -> Result<Cow<'de, [u8]>> {
+        if !self.escaped {
+            return Ok(self.escaped_value);
+        }
+        match self.escaped_value {
+            Cow::Borrowed(value) => self
+                .options
+                .unescape(value)
+                .map_err(|e| self.error(Reason::Xml(e))),
+            Cow::Owned(_) => self.unescaped().map(|value| Cow::Owned(value.into_owned())),
+        }
+    }
+
     fn error(&self, reason: Reason) -> Error {
-        Error::new(reason, 0)
+        Error::new(reason, self.offset)
     }
 
-    fn from_utf8(v: &[u8]) -> Result<&str> {
-        from_utf8(&v).map_err(|err| Error::new(Reason::Xml(XmlError::Utf8(err)), err.valid_up_to()))
+    fn from_utf8(v: &[u8], offset: usize) -> Result<&str> {
+        from_utf8(v)
+            .map_err(|err| Error::new(Reason::Xml(XmlError::Utf8(err)), offset + err.valid_up_to()))
     }
 }
 
@@ -57,15 +85,17 @@ macro_rules! deserialize_num {
         where
             V: Visitor<'de>,
         {
-            let value = Self::from_utf8(&self.escaped_value)?
+            let value = Self::from_utf8(&self.escaped_value, self.offset)?
                 .parse::<$ty>()
-                .at_offset(0)?;
-            visitor.$visit(value)
+                .at_offset(self.offset)?;
+            visitor
+                .$visit(value)
+                .map_err(|err: Error| err.fix_offset(self.offset))
         }
     };
 }
 
-impl<'de> serde::Deserializer<'de> for EscapedDeserializer {
+impl<'de> serde::Deserializer<'de> for EscapedDeserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
@@ -79,10 +109,13 @@ impl<'de> serde::Deserializer<'de> for EscapedDeserializer {
     where
         V: Visitor<'de>,
     {
-        match &*self.escaped_value {
-            b"true" | b"1" => visitor.visit_bool(true),
-            b"false" | b"0" => visitor.visit_bool(false),
-            e => Err(self.error(Reason::InvalidBoolean(Self::from_utf8(e)?.into()))),
+        match self.options.booleans.parse(&self.escaped_value) {
+            Some(value) => visitor
+                .visit_bool(value)
+                .map_err(|err: Error| err.fix_offset(self.offset)),
+            None => Err(self.error(Reason::InvalidBoolean(
+                Self::from_utf8(&self.escaped_value, self.offset)?.into(),
+            ))),
         }
     }
 
@@ -97,9 +130,12 @@ impl<'de> serde::Deserializer<'de> for EscapedDeserializer {
     where
         V: Visitor<'de>,
     {
-        let unescaped = self.unescaped()?;
-        let value = Self::from_utf8(&unescaped)?;
-        visitor.visit_str(&value)
+        let offset = self.offset;
+        match self.into_unescaped()? {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(Self::from_utf8(value, offset)?),
+            Cow::Owned(value) => visitor.visit_str(Self::from_utf8(&value, offset)?),
+        }
+        .map_err(|err: Error| err.fix_offset(offset))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -113,8 +149,14 @@ impl<'de> serde::Deserializer<'de> for EscapedDeserializer {
     where
         V: Visitor<'de>,
     {
-        let v = self.unescaped()?;
-        visitor.visit_bytes(&v)
+        let value = self
+            .options
+            .binary_encoding
+            .decode(&self.unescaped()?)
+            .map_err(|reason| self.error(reason))?;
+        visitor
+            .visit_byte_buf(value)
+            .map_err(|err: Error| err.fix_offset(self.offset))
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
@@ -180,7 +222,7 @@ impl<'de> serde::Deserializer<'de> for EscapedDeserializer {
     }
 }
 
-impl<'de> de::EnumAccess<'de> for EscapedDeserializer {
+impl<'de> de::EnumAccess<'de> for EscapedDeserializer<'de> {
     type Error = Error;
     type Variant = Self;
 
@@ -190,7 +232,7 @@ impl<'de> de::EnumAccess<'de> for EscapedDeserializer {
     }
 }
 
-impl<'de> de::VariantAccess<'de> for EscapedDeserializer {
+impl<'de> de::VariantAccess<'de> for EscapedDeserializer<'de> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -0,0 +1,84 @@
+//! Streaming over repeated elements
+
+use std::marker::PhantomData;
+
+use quick_xml::events::Event;
+use serde::de::Deserialize;
+
+use crate::de::{Deserializer, XmlRead};
+use crate::Error;
+
+/// An iterator over all elements matching a path, see [`Deserializer::iter_path`]
+pub struct PathIter<'de, 'a, R: XmlRead<'de>, T> {
+    de: &'a mut Deserializer<'de, R>,
+    /// Element names from the root to the items
+    path: Vec<Vec<u8>>,
+    /// Number of currently open elements, which all match the path
+    depth: usize,
+    /// Iteration stopped at the end of the document or after an error
+    done: bool,
+    output: PhantomData<T>,
+}
+
+impl<'de, 'a, R: XmlRead<'de>, T> PathIter<'de, 'a, R, T> {
+    pub(crate) fn new(de: &'a mut Deserializer<'de, R>, path: &str) -> Self {
+        PathIter {
+            de,
+            path: path
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .map(|name| name.as_bytes().to_vec())
+                .collect(),
+            depth: 0,
+            done: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Skips to the start of the next matching element, which is left in the peek slot
+    fn next_item(&mut self) -> Result<bool, Error> {
+        loop {
+            match self.de.next()? {
+                Event::Start(e) => {
+                    let matches = self
+                        .path
+                        .get(self.depth)
+                        .map_or(false, |name| *name == *self.de.element_key(&e));
+                    if !matches {
+                        self.de.skip_to_end(e.name(), false)?;
+                    } else if self.depth + 1 == self.path.len() {
+                        self.de.peek = Some(Event::Start(e));
+                        return Ok(true);
+                    } else {
+                        self.depth += 1;
+                    }
+                }
+                // end of an element around the path
+                Event::End(_) if self.depth == 0 => return Ok(false),
+                Event::End(_) => self.depth -= 1,
+                Event::Eof => return Ok(false),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<'de, 'a, R: XmlRead<'de>, T: Deserialize<'de>> Iterator for PathIter<'de, 'a, R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.path.is_empty() {
+            return None;
+        }
+        let item = match self.next_item() {
+            Ok(true) => T::deserialize(&mut *self.de).map_err(|err| self.de.fix_position(err)),
+            Ok(false) => {
+                self.done = true;
+                return None;
+            }
+            Err(err) => Err(err),
+        };
+        self.done = item.is_err();
+        Some(item)
+    }
+}
@@ -1,32 +1,47 @@
 //! Serde `Deserializer` module
 
-use std::io::BufRead;
+use std::borrow::Cow;
 
 use quick_xml::events::{BytesStart, Event};
-use serde::de::{self, DeserializeSeed, IntoDeserializer};
+use serde::de::{self, DeserializeSeed, IgnoredAny, IntoDeserializer};
 
 use crate::error::Reason;
 use crate::{
-    de::{escape::EscapedDeserializer, Deserializer, INNER_VALUE},
+    de::{
+        is_schema_attribute, Deserializer, UnknownFields, XmlRead, ANY_FIELDS, INNER_VALUE,
+        TAG_NAME,
+    },
     Error,
 };
 use std::vec;
 
-enum MapValue {
+enum MapValue<'de> {
     Empty,
-    Attribute { value: Vec<u8> },
+    Attribute {
+        key: Vec<u8>,
+        value: Cow<'de, [u8]>,
+        offset: usize,
+        start: usize,
+    },
     Nested,
     InnerValue,
 }
 
+/// Key and value of an attribute with the offset of the value and of the attribute
+type Attribute<'de> = (Vec<u8>, Cow<'de, [u8]>, usize, usize);
+
 /// A deserializer for `Attributes`
-pub(crate) struct MapAccess<'a, R: BufRead> {
-    de: &'a mut Deserializer<R>,
-    attributes: vec::IntoIter<(Vec<u8>, Vec<u8>)>,
-    value: MapValue,
+pub(crate) struct MapAccess<'de, 'a, R: XmlRead<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+    attributes: vec::IntoIter<Attribute<'de>>,
+    value: MapValue<'de>,
+    /// Fields of the structure, `None` for maps
+    fields: Option<&'static [&'static str]>,
+    /// A child element was read
+    children: bool,
 }
 
-impl<'a, R: BufRead> MapAccess<'a, R> {
+impl<'de, 'a, R: XmlRead<'de>> MapAccess<'de, 'a, R> {
     fn create_attr_key(key: &[u8]) -> Vec<u8> {
         let mut result = Vec::with_capacity(key.len() + 1);
         result.push(b'@');
@@ -34,70 +49,180 @@ impl<'a, R: BufRead> MapAccess<'a, R> {
         result
     }
 
-    /// Create a new MapAccess
-    pub fn new(de: &'a mut Deserializer<R>, start: &BytesStart<'static>) -> Result<Self, Error> {
-        // TODO: optimize copies!
-        let attributes = start
-            .attributes()
-            .map(|a| {
-                let a = a?;
-                Ok((Self::create_attr_key(a.key), a.value.into_owned()))
-            })
-            .collect::<Result<Vec<(Vec<u8>, Vec<u8>)>, Error>>()?
-            .into_iter();
+    /// Create a new MapAccess for a structure with `fields` or a map without
+    ///
+    /// With a `$tag` field the tag name is provided as first entry with this key.
+    pub fn new(
+        de: &'a mut Deserializer<'de, R>,
+        start: &BytesStart<'de>,
+        fields: Option<&'static [&'static str]>,
+    ) -> Result<Self, Error> {
+        de.field_element = false;
+        let ignored = de.ignored_attribute.take();
+        let start_offset = de.reader.event_offset();
+        // offset of a slice of `start` in the input
+        let offset = |slice: &[u8]| {
+            start_offset + (slice.as_ptr() as usize).saturating_sub(start.as_ptr() as usize)
+        };
+        let mut attributes = Vec::new();
+        if fields.map_or(false, |fields| fields.contains(&TAG_NAME)) {
+            attributes.push((
+                TAG_NAME.as_bytes().to_vec(),
+                de.borrow_or_copy(start.name()),
+                start_offset,
+                start_offset,
+            ));
+        }
+        for a in start.attributes() {
+            let a = a.map_err(|err| de.fix_position(err.into()))?;
+            match de.attribute_name(a.key) {
+                Some(key) if Some(&*key) != ignored.as_deref() => attributes.push((
+                    Self::create_attr_key(&key),
+                    de.borrow_or_copy(&a.value),
+                    offset(&a.value),
+                    offset(a.key),
+                )),
+                _ => (),
+            }
+        }
+        let attributes = attributes.into_iter();
         Ok(MapAccess {
             de,
             attributes,
             value: MapValue::Empty,
+            fields,
+            children: false,
         })
     }
+
+    /// Provide already read `text` as first `$value` after the attributes
+    pub fn with_text(mut self, text: Cow<'de, [u8]>, offset: usize) -> Self {
+        let mut attributes: Vec<_> = self.attributes.collect();
+        attributes.push((INNER_VALUE.as_bytes().to_vec(), text, offset, offset));
+        self.attributes = attributes.into_iter();
+        self
+    }
+
+    fn is_field(&self, key: &[u8]) -> bool {
+        self.fields
+            .unwrap_or_default()
+            .iter()
+            .any(|field| field.as_bytes() == key)
+    }
+
+    /// Skips whitespace-only text, which is not content of a `$value` field
+    ///
+    /// Untyped values keep all text. Whitespace before a field or after the last child element is
+    /// never content.
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        if self.is_field(ANY_FIELDS.as_bytes()) {
+            return Ok(());
+        }
+        if self.is_field(INNER_VALUE.as_bytes()) {
+            self.de.peek_after_whitespace()?;
+            let content = match self.de.after_peek.as_ref().or(self.de.peek.as_ref()) {
+                Some(Event::Start(e)) => !self.is_field(&self.de.element_key(e)),
+                Some(Event::Text(_)) | Some(Event::CData(_)) => true,
+                _ => !self.children,
+            };
+            if content {
+                return Ok(());
+            }
+        }
+        self.de.skip_whitespace()
+    }
+
+    /// Element or attribute `key`, which is no field of the structure
+    ///
+    /// Maps and untyped values have no unknown keys.
+    fn is_unknown(&self, key: &[u8]) -> bool {
+        if self.fields.is_none() || self.is_field(ANY_FIELDS.as_bytes()) || self.is_field(key) {
+            return false;
+        }
+        match key.strip_prefix(b"@") {
+            Some(name) => !is_schema_attribute(name),
+            None => true,
+        }
+    }
+
+    /// Report an element or attribute `key` at `offset`, which is no field, depending on the
+    /// options
+    fn check_field(&mut self, key: &[u8], offset: usize) -> Result<(), Error> {
+        if self.de.options.unknown_fields == UnknownFields::Ignore || !self.is_unknown(key) {
+            return Ok(());
+        }
+        let err = match key.strip_prefix(b"@") {
+            Some(name) => {
+                let name = String::from_utf8_lossy(name).into_owned();
+                Error::new(Reason::UnknownAttribute(name), offset)
+                    .fix_path(|| self.de.path.attribute(key))
+            }
+            None => {
+                let name = String::from_utf8_lossy(key).into_owned();
+                Error::new(Reason::UnknownElement(name), offset)
+            }
+        };
+        self.de.unknown(err)
+    }
 }
 
-impl<'a, 'de, R: BufRead> de::MapAccess<'de> for MapAccess<'a, R> {
+impl<'de, 'a, R: XmlRead<'de>> de::MapAccess<'de> for MapAccess<'de, 'a, R> {
     type Error = Error;
 
     fn next_key_seed<K: DeserializeSeed<'de>>(
         &mut self,
         seed: K,
     ) -> Result<Option<K::Value>, Self::Error> {
-        if let Some((key, value)) = self.attributes.next() {
+        if let Some((key, value, offset, start)) = self.attributes.next() {
             // try getting map from attributes (key= "value")
-            self.value = MapValue::Attribute { value };
-            seed.deserialize(EscapedDeserializer::new(key, false))
+            self.check_field(&key, offset)?;
+            self.value = MapValue::Attribute {
+                key: key.clone(),
+                value,
+                offset,
+                start,
+            };
+            seed.deserialize(self.de.escaped(key.into(), false, offset))
                 .map(Some)
         } else {
-            let has_value_field = self.de.has_value_field;
-
             // try getting from events (<key>value</key>)
+            self.skip_whitespace()?;
             match self.de.peek()? {
-                Some(Event::Text(_)) => {
+                Some(Event::Text(_)) | Some(Event::CData(_)) => {
                     self.value = MapValue::InnerValue;
                     seed.deserialize(INNER_VALUE.into_deserializer()).map(Some)
                 }
-                // Used to deserialize collections of enums, like:
-                // <root>
-                //   <A/>
-                //   <B/>
-                //   <C/>
-                // </root>
-                //
-                // into
-                //
-                // enum Enum { A, B, С }
-                // struct Root {
-                //     #[serde(rename = "$value")]
-                //     items: Vec<Enum>,
-                // }
-                // TODO: This should be handled by #[serde(flatten)]
-                // See https://github.com/serde-rs/serde/issues/1905
-                Some(Event::Start(_)) if has_value_field => {
-                    self.value = MapValue::InnerValue;
-                    seed.deserialize(INNER_VALUE.into_deserializer()).map(Some)
-                }
-                Some(Event::Start(e)) => {
-                    let name = e.local_name().to_owned();
+                Some(Event::Start(_)) => {
+                    self.children = true;
+                    let name = match &self.de.peek {
+                        Some(Event::Start(e)) => self.de.element_key(e).into_owned(),
+                        _ => unreachable!(),
+                    };
+                    // Elements, which are no fields, are part of the inner value, like:
+                    // <root id="1">
+                    //   <name>build</name>
+                    //   <A/>
+                    //   <B/>
+                    // </root>
+                    //
+                    // into
+                    //
+                    // enum Step { A, B }
+                    // struct Root {
+                    //     #[serde(rename = "@id")]
+                    //     id: u32,
+                    //     name: String,
+                    //     #[serde(rename = "$value")]
+                    //     steps: Vec<Step>,
+                    // }
+                    if self.is_field(INNER_VALUE.as_bytes()) && !self.is_field(&name) {
+                        self.value = MapValue::InnerValue;
+                        return seed.deserialize(INNER_VALUE.into_deserializer()).map(Some);
+                    }
+                    let offset = self.de.reader.event_offset();
+                    self.check_field(&name, offset)?;
                     self.value = MapValue::Nested;
-                    seed.deserialize(EscapedDeserializer::new(name, false))
+                    seed.deserialize(self.de.escaped(name.into(), false, offset))
                         .map(Some)
                 }
                 _ => Ok(None),
@@ -110,11 +235,123 @@ impl<'a, 'de, R: BufRead> de::MapAccess<'de> for MapAccess<'a, R> {
         seed: K,
     ) -> Result<K::Value, Self::Error> {
         match std::mem::replace(&mut self.value, MapValue::Empty) {
-            MapValue::Attribute { value } => {
-                seed.deserialize(EscapedDeserializer::new(value, true))
+            MapValue::Attribute {
+                key,
+                value,
+                offset,
+                start,
+            } => {
+                if self.de.skip_hook.is_some() && self.is_unknown(&key) {
+                    // the value ends before the closing quote
+                    let path = self.de.path.attribute(&key);
+                    self.de
+                        .skipped(&path, &key, start..offset + value.len() + 1);
+                }
+                seed.deserialize(self.de.escaped(value, true, offset))
+                    .map_err(|err| {
+                        let err = err
+                            .fix_offset(offset)
+                            .fix_path(|| self.de.path.attribute(&key));
+                        self.de.fix_position(err)
+                    })
+            }
+            MapValue::Nested => {
+                self.de.field_element = true;
+                seed.deserialize(&mut *self.de)
+            }
+            MapValue::InnerValue => {
+                self.de.field_element = false;
+                // a sequence as inner value stops at the next field
+                self.de.value_fields = self.fields;
+                let result = seed.deserialize(&mut *self.de);
+                self.de.value_fields = None;
+                result
             }
-            MapValue::Nested | MapValue::InnerValue => seed.deserialize(&mut *self.de),
             MapValue::Empty => Err(self.de.error(Reason::EndOfAttributes)),
         }
     }
 }
+
+/// Access to a map written as `<entry key="k">value</entry>` elements
+pub(crate) struct EntryAccess<'de, 'a, R: XmlRead<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+    /// Name of the entry elements
+    element: Vec<u8>,
+    /// Name of the attribute containing the key
+    key: Vec<u8>,
+}
+
+impl<'de, 'a, R: XmlRead<'de>> EntryAccess<'de, 'a, R> {
+    pub fn new(de: &'a mut Deserializer<'de, R>, element: Vec<u8>, key: Vec<u8>) -> Self {
+        EntryAccess { de, element, key }
+    }
+}
+
+impl<'de, 'a, R: XmlRead<'de>> de::MapAccess<'de> for EntryAccess<'de, 'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        loop {
+            self.de.peek()?;
+            let is_entry = match &self.de.peek {
+                Some(Event::Start(e)) => self.de.element_key(e) == self.element,
+                Some(Event::Text(_)) | Some(Event::CData(_)) => false,
+                _ => return Ok(None),
+            };
+            if !is_entry {
+                // other elements are unknown, like elements of a structure without field
+                if let Some(Event::Start(e)) = &self.de.peek {
+                    let name = String::from_utf8_lossy(&self.de.element_key(e)).into_owned();
+                    let offset = self.de.reader.event_offset();
+                    self.de
+                        .unknown(Error::new(Reason::UnknownElement(name), offset))?;
+                }
+                de::Deserializer::deserialize_ignored_any(&mut *self.de, IgnoredAny)?;
+                continue;
+            }
+
+            let start = match &self.de.peek {
+                Some(Event::Start(start)) => start,
+                _ => unreachable!(),
+            };
+            let mut key = None;
+            for a in start.attributes() {
+                let a = a.map_err(|err| self.de.fix_position(err.into()))?;
+                match self.de.attribute_name(a.key) {
+                    Some(name) if *name == *self.key => {
+                        let offset = self.de.reader.event_offset()
+                            + (a.value.as_ptr() as usize - start.as_ptr() as usize);
+                        key = Some((self.de.borrow_or_copy(&a.value), offset));
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+
+            let (key, offset) = key.ok_or_else(|| {
+                self.de.error(Reason::Message(format!(
+                    "missing attribute `{}` of map entry",
+                    String::from_utf8_lossy(&self.key)
+                )))
+            })?;
+            return seed
+                .deserialize(self.de.escaped(key, true, offset))
+                .map(Some);
+        }
+    }
+
+    fn next_value_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<K::Value, Self::Error> {
+        // the value gets the entry without the key attribute
+        self.de.field_element = true;
+        self.de.ignored_attribute = Some(self.key.clone());
+        let value = seed.deserialize(&mut *self.de);
+        self.de.ignored_attribute = None;
+        value
+    }
+}
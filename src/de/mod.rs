@@ -107,95 +107,574 @@
 //! }
 //! ```
 
+use std::borrow::Cow;
 use std::io::BufRead;
+use std::ops::Range;
+use std::rc::Rc;
 
 use quick_xml::{
     events::{BytesStart, BytesText, Event},
-    Error as XmlError, Reader,
+    Reader,
 };
-use serde::de::{self, DeserializeOwned, Visitor};
-use serde::serde_if_integer128;
+use serde::de::{self, Deserialize, DeserializeOwned, Visitor};
 
+use self::escape::EscapedDeserializer;
+use self::path::ElementPath;
+use crate::chars::normalize_line_endings;
+use crate::entries::{ENTRY, ENTRY_KEY, MAP_ENTRIES};
 use crate::error::Reason;
 use crate::error::ResultExt;
-use crate::Error;
-use std::str::{from_utf8, Utf8Error};
+use crate::prolog::Prolog;
+use crate::{Encoding, Error, XmlVersion, XML_NAMESPACE};
+
+pub use self::iter::PathIter;
+pub use self::options::{DeserializerOptions, UnknownFields};
+pub use self::read::{DecodingReader, IoReader, SliceReader, XmlRead};
 
 mod escape;
+mod iter;
 mod map;
+mod options;
+mod path;
+mod read;
 mod seq;
 mod var;
 
-const INNER_VALUE: &str = "$value";
+/// Field name for the inner value of an element
+pub(crate) const INNER_VALUE: &str = "$value";
+
+/// Field name for the tag name of an element
+///
+/// The tag name is only provided when the structure has a field with this name.
+pub(crate) const TAG_NAME: &str = "$tag";
+
+/// Field name, which marks structures taking any element, like [`Element`](crate::element::Element)
+pub(crate) const ANY_FIELDS: &str = "$any";
+
+/// Variant name for text in mixed content
+///
+/// Enums with a variant of this name read text as this variant instead of as variant name.
+pub(crate) const TEXT: &str = "$text";
+
+/// Namespace of attributes like `xsi:type` and `xsi:nil`
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// Attributes of XML itself and of XML Schema instances, which are no data: namespace
+/// declarations, `xml:*` and `xsi:*`
+pub(crate) fn is_schema_attribute(name: &[u8]) -> bool {
+    name == b"xmlns"
+        || name.starts_with(b"xmlns:")
+        || name.starts_with(b"xml:")
+        || name.starts_with(b"xsi:")
+        || (name.starts_with(b"{") && name[1..].starts_with(XSI_NAMESPACE.as_bytes()))
+}
 
 /// An xml deserializer
-pub struct Deserializer<R: BufRead> {
-    reader: Reader<R>,
-    peek: Option<Event<'static>>,
-    has_value_field: bool,
+pub struct Deserializer<'de, R: XmlRead<'de>> {
+    reader: R,
+    peek: Option<Event<'de>>,
+    /// Event after `peek`, when `peek` is whitespace-only text, see `peek_after_whitespace`
+    after_peek: Option<Event<'de>>,
+    /// Fields of the structure, whose inner value is deserialized
+    value_fields: Option<&'static [&'static str]>,
+    /// The next element is a field or a named sequence item, which may wrap an enum variant
+    field_element: bool,
+    /// Offset of the peeked element of a newtype variant, a sequence is read from its content
+    variant_start: Option<usize>,
+    /// The next text is read by the `$text` variant, so it is not trimmed
+    keep_text: bool,
+    /// Namespace declarations in scope, used by `reader`
+    namespace_buffer: Vec<u8>,
+    options: Rc<DeserializerOptions>,
+    /// Offset of the last text returned by `next_text`
+    text_offset: usize,
+    /// Position of the reader after the last text returned by `next_text`
+    text_end: Option<usize>,
+    /// Location of the last event for errors
+    path: ElementPath,
+    /// Declarations and processing instructions before the root element
+    prolog: Prolog,
+    /// The root element was read
+    after_prolog: bool,
+    /// The next map is read from entries, see [`entries`](crate::entries)
+    map_entries: bool,
+    /// Attribute of the next start element, which is not part of its value, like the key of a
+    /// map entry
+    ignored_attribute: Option<Vec<u8>>,
+    /// Unknown elements and attributes found with `UnknownFields::Warn`
+    warnings: Vec<Error>,
+    /// Called for skipped elements and attributes
+    skip_hook: Option<SkipHook<'de>>,
+}
+
+/// Callback for skipped elements and attributes
+type SkipHook<'de> = Box<dyn FnMut(&Skipped) + 'de>;
+
+/// Element or attribute, which was skipped by the [`Deserializer`]
+///
+/// See [`Deserializer::on_skipped`].
+#[derive(Debug)]
+pub struct Skipped<'a> {
+    path: &'a str,
+    name: &'a str,
+    range: Range<usize>,
+}
+
+impl<'a> Skipped<'a> {
+    /// Path of the element or attribute, like [`Error::path`]
+    pub fn path(&self) -> &str {
+        self.path
+    }
+
+    /// Name like a field, e.g. `name` for an element or `@name` for an attribute
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Byte range of the complete element or attribute in the input
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
 }
 
 /// Deserialize a xml string
-pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
-    from_reader(s.as_bytes())
+///
+/// Text and attribute values without escapes are borrowed from `s`. The string is always UTF-8,
+/// so the encoding in the XML declaration is ignored.
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+    DeserializerOptions::new().from_str(s)
+}
+
+/// Deserialize from bytes
+///
+/// Text and attribute values without escapes are borrowed from `v`, when it is UTF-8 encoded.
+/// Other encodings are detected like in [`from_reader`].
+pub fn from_slice<'de, T: Deserialize<'de>>(v: &'de [u8]) -> Result<T, Error> {
+    DeserializerOptions::new().from_slice(v)
 }
 
 /// Deserialize from a reader
+///
+/// The document is decoded according to its byte order mark or XML declaration, see
+/// [`Encoding`] for the supported encodings.
 pub fn from_reader<R: BufRead, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
-    let mut de = Deserializer::from_reader(reader);
-    T::deserialize(&mut de)
+    DeserializerOptions::new().from_reader(reader)
 }
 
-impl<R: BufRead> Deserializer<R> {
+impl<'de, R: BufRead> Deserializer<'de, IoReader<R>> {
     /// Get a new deserializer
+    ///
+    /// The `trim_text` setting of `reader` is ignored. Whitespace is trimmed unless it is
+    /// preserved with [`DeserializerOptions::preserve_whitespace`] or `xml:space`.
     pub fn new(reader: Reader<R>) -> Self {
-        Deserializer {
-            reader,
-            peek: None,
-            has_value_field: false,
-        }
+        Self::with_reader(IoReader::new(reader))
     }
+}
 
+impl<'de, R: BufRead> Deserializer<'de, IoReader<DecodingReader<R>>> {
     /// Get a new deserializer from a regular BufRead
+    ///
+    /// The document is decoded according to its byte order mark or XML declaration. Offsets
+    /// in errors refer to the document transcoded to UTF-8.
     pub fn from_reader(reader: R) -> Self {
+        Self::new(Self::configure(DecodingReader::new(reader)))
+    }
+
+    /// Decode the document as `encoding`, regardless of its XML declaration
+    ///
+    /// Has to be called before deserializing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::{Deserializer, Encoding};
+    ///
+    /// // declared wrongly by the producer
+    /// let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><name>M\xFCller</name>";
+    ///
+    /// let mut de = Deserializer::from_reader(&xml[..]).with_encoding(Encoding::Iso8859_1);
+    /// assert_eq!(String::deserialize(&mut de).unwrap(), "Müller");
+    /// ```
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        let reader = self.reader.into_inner().into_underlying_reader();
+        self.reader = IoReader::new(Self::configure(DecodingReader::with_encoding(
+            reader.into_inner(),
+            encoding,
+        )));
+        self.reader.check_end_names(self.options.check_end_names);
+        self
+    }
+
+    fn configure(reader: DecodingReader<R>) -> Reader<DecodingReader<R>> {
         let mut reader = Reader::from_reader(reader);
+        reader.expand_empty_elements(true).check_end_names(true);
         reader
-            .expand_empty_elements(true)
-            .check_end_names(true)
-            .trim_text(true);
-        Self::new(reader)
+    }
+}
+
+impl<'de> Deserializer<'de, SliceReader<'de>> {
+    /// Get a new deserializer, which borrows from a string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'de str) -> Self {
+        Self::from_slice(s.as_bytes())
+    }
+
+    /// Get a new deserializer, which borrows from UTF-8 encoded bytes
+    ///
+    /// A byte order mark is skipped.
+    pub fn from_slice(v: &'de [u8]) -> Self {
+        let bom = Encoding::Utf8.bom_len(v);
+        Self::with_reader(SliceReader::new(&v[bom..]))
+    }
+}
+
+impl<'de, R: XmlRead<'de>> Deserializer<'de, R> {
+    /// Get a new deserializer from any source of events
+    pub fn with_reader(reader: R) -> Self {
+        Deserializer {
+            reader,
+            peek: None,
+            after_peek: None,
+            value_fields: None,
+            field_element: false,
+            variant_start: None,
+            keep_text: false,
+            namespace_buffer: Vec::new(),
+            options: Rc::new(DeserializerOptions::default()),
+            text_offset: 0,
+            text_end: None,
+            path: ElementPath::new(),
+            prolog: Prolog::new(),
+            after_prolog: false,
+            map_entries: false,
+            ignored_attribute: None,
+            warnings: Vec::new(),
+            skip_hook: None,
+        }
+    }
+
+    /// Call `hook` for every element and attribute, which is skipped because the data type has
+    /// no field for it, including attributes and child elements of primitive values
+    ///
+    /// Text, namespace declarations and attributes like `xml:space` or `xsi:type` are not
+    /// reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::Deserializer;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     timeout: Option<u32>,
+    /// }
+    ///
+    /// let xml = r#"<config mode="fast"><timout>5</timout></config>"#;
+    /// let mut skipped = Vec::new();
+    /// let mut de = Deserializer::from_str(xml).on_skipped(|skipped_content| {
+    ///     skipped.push((skipped_content.path().to_string(), skipped_content.range()));
+    /// });
+    /// Config::deserialize(&mut de).unwrap();
+    /// drop(de);
+    ///
+    /// assert_eq!(skipped[0], ("config/@mode".to_string(), 8..19));
+    /// assert_eq!(skipped[1], ("config/timout[0]".to_string(), 20..38));
+    /// assert_eq!(&xml[20..38], "<timout>5</timout>");
+    /// ```
+    pub fn on_skipped<F: FnMut(&Skipped) + 'de>(mut self, hook: F) -> Self {
+        self.skip_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Options of the deserializer, [`DeserializerOptions::default`] otherwise
+    ///
+    /// Has to be called before deserializing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::de::{DeserializerOptions, UnknownFields};
+    /// use xserde::Deserializer;
+    ///
+    /// let options = DeserializerOptions::new()
+    ///     .preserve_whitespace(true)
+    ///     .with_unknown_fields(UnknownFields::Error);
+    /// let mut de = Deserializer::from_str("<a> x </a>").with_options(options);
+    /// assert_eq!(String::deserialize(&mut de).unwrap(), " x ");
+    /// ```
+    pub fn with_options(mut self, options: DeserializerOptions) -> Self {
+        self.reader.check_end_names(options.check_end_names);
+        self.options = Rc::new(options);
+        self
+    }
+
+    /// Declarations and processing instructions before the root element
+    ///
+    /// Complete after the start of the root element was read, e.g. after deserializing.
+    pub fn prolog(&self) -> &Prolog {
+        &self.prolog
+    }
+
+    /// Unknown elements and attributes skipped with [`UnknownFields::Warn`]
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// XML version of the document, 1.0 without XML declaration
+    ///
+    /// XML 1.1 documents can also end lines with NEL and LS, which are read as `\n`.
+    pub fn version(&self) -> XmlVersion {
+        self.prolog.version().unwrap_or(XmlVersion::v1_0)
     }
 
-    fn peek(&mut self) -> Result<Option<&Event<'static>>, Error> {
+    /// Deserialize every element at `path` one after another
+    ///
+    /// `path` are element names from the root element separated by `/`. Names are matched like
+    /// struct fields. Everything else in the document is skipped without being buffered, so
+    /// documents of any size can be processed.
+    ///
+    /// The iterator stops after the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::Deserializer;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Point {
+    ///     #[serde(rename = "@lat")]
+    ///     lat: f64,
+    ///     #[serde(rename = "@lon")]
+    ///     lon: f64,
+    /// }
+    ///
+    /// let xml = r#"<gpx>
+    ///     <metadata><name>Track</name></metadata>
+    ///     <trk>
+    ///         <trkseg><trkpt lat="1" lon="2"/><trkpt lat="3" lon="4"/></trkseg>
+    ///         <trkseg><trkpt lat="5" lon="6"/></trkseg>
+    ///     </trk>
+    /// </gpx>"#;
+    ///
+    /// let mut de = Deserializer::from_reader(xml.as_bytes());
+    /// let points: Vec<Point> = de
+    ///     .iter_path("gpx/trk/trkseg/trkpt")
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(points.len(), 3);
+    /// assert_eq!(points[2], Point { lat: 5.0, lon: 6.0 });
+    /// ```
+    pub fn iter_path<T: Deserialize<'de>>(&mut self, path: &str) -> PathIter<'de, '_, R, T> {
+        PathIter::new(self, path)
+    }
+
+    /// Name as used for matching map keys
+    fn qualified_name(&self, namespace: Option<&[u8]>, local_name: &[u8]) -> Vec<u8> {
+        match namespace {
+            None => local_name.to_vec(),
+            Some(uri) => match self.options.prefixes.get(uri) {
+                Some(prefix) if prefix.is_empty() => local_name.to_vec(),
+                Some(prefix) => [prefix, &b":"[..], local_name].concat(),
+                None => [&b"{"[..], uri, &b"}"[..], local_name].concat(),
+            },
+        }
+    }
+
+    /// Name of an element as written in the document or with resolved namespace
+    ///
+    /// Namespaces can only be resolved for the last read start element.
+    pub(crate) fn element_name<'a>(&self, start: &'a BytesStart) -> Cow<'a, [u8]> {
+        if self.options.resolve_namespaces {
+            let (namespace, local_name) = self
+                .reader
+                .reader()
+                .event_namespace(start.name(), &self.namespace_buffer);
+            Cow::Owned(self.qualified_name(namespace, local_name))
+        } else {
+            Cow::Borrowed(start.name())
+        }
+    }
+
+    /// Key of an element for a map: the local name or the name with resolved namespace
+    ///
+    /// Namespaces can only be resolved for the last read start element.
+    pub(crate) fn element_key<'a>(&self, start: &'a BytesStart) -> Cow<'a, [u8]> {
+        if self.options.resolve_namespaces {
+            self.element_name(start)
+        } else {
+            Cow::Borrowed(start.local_name())
+        }
+    }
+
+    /// Name of an attribute as written in the document or with resolved namespace
+    ///
+    /// Returns `None` for namespace declarations when namespaces are resolved.
+    pub(crate) fn attribute_name<'a>(&self, name: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        if !self.options.resolve_namespaces {
+            return Some(Cow::Borrowed(name));
+        }
+        if name == b"xmlns" || name.starts_with(b"xmlns:") {
+            return None;
+        }
+        let (namespace, local_name) = match name.strip_prefix(b"xml:") {
+            Some(local_name) => (Some(XML_NAMESPACE.as_bytes()), local_name),
+            None => self
+                .reader
+                .reader()
+                .attribute_namespace(name, &self.namespace_buffer),
+        };
+        Some(Cow::Owned(self.qualified_name(namespace, local_name)))
+    }
+
+    /// Content of an event, borrowed from the input if possible
+    pub(crate) fn borrow_or_copy(&self, content: &[u8]) -> Cow<'de, [u8]> {
+        match self.reader.borrow(content) {
+            Some(content) => Cow::Borrowed(content),
+            None => Cow::Owned(content.to_vec()),
+        }
+    }
+
+    /// Whitespace around texts in the current element is removed
+    fn trims_text(&self) -> bool {
+        !self
+            .path
+            .preserve_space()
+            .unwrap_or(self.options.preserve_whitespace)
+    }
+
+    /// Content of `text` at `offset` and its offset, without surrounding whitespace unless
+    /// whitespace is preserved
+    ///
+    /// Texts in the root element are trimmed here instead of by the reader, so that mixed content
+    /// keeps its whitespace.
+    pub(crate) fn text_content(&self, text: &[u8], offset: usize) -> (Cow<'de, [u8]>, usize) {
+        if !self.trims_text() {
+            return (self.borrow_or_copy(text), offset);
+        }
+        let (start, end) = read::trim(text).unwrap_or((0, 0));
+        (self.borrow_or_copy(&text[start..end]), offset + start)
+    }
+
+    fn peek(&mut self) -> Result<Option<&Event<'de>>, Error> {
         if self.peek.is_none() {
-            self.peek = Some(self.next(&mut Vec::new())?);
+            self.peek = Some(self.next()?);
         }
         Ok(self.peek.as_ref())
     }
 
-    fn next<'a>(&mut self, buf: &'a mut Vec<u8>) -> Result<Event<'static>, Error> {
+    /// Whitespace-only text was peeked, which is only read when whitespace is preserved
+    fn peek_whitespace(&mut self) -> Result<bool, Error> {
+        Ok(match self.peek()? {
+            Some(Event::Text(text)) => text.iter().all(|b| b" \t\r\n".contains(b)),
+            _ => false,
+        })
+    }
+
+    /// Skips a peeked whitespace-only text
+    ///
+    /// Whitespace between elements is only content, when the data type takes text.
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        if self.peek_whitespace()? {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    /// Peeks the event after whitespace-only text, which stays peeked
+    fn peek_after_whitespace(&mut self) -> Result<Option<&Event<'de>>, Error> {
+        if !self.peek_whitespace()? {
+            return Ok(self.peek.as_ref());
+        }
+        if self.after_peek.is_none() {
+            self.after_peek = Some(self.read_next()?);
+        }
+        Ok(self.after_peek.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Event<'de>, Error> {
         if let Some(e) = self.peek.take() {
+            self.peek = self.after_peek.take();
             return Ok(e);
         }
+        self.read_next()
+    }
+
+    /// Reads the next event from `reader`
+    fn read_next(&mut self) -> Result<Event<'de>, Error> {
         loop {
-            let e = self.reader.read_event(buf)?;
+            // whitespace outside of the root element is never kept, texts inside are trimmed when
+            // they are read
+            self.reader.trim_text(self.path.depth() == 0);
+            let namespace_buffer = if self.options.resolve_namespaces {
+                Some(&mut self.namespace_buffer)
+            } else {
+                None
+            };
+            let e = self
+                .reader
+                .next(namespace_buffer)
+                .map_err(|err| self.fix_position(err))?;
+            match &e {
+                Event::Start(start) => {
+                    self.path.start(start.name());
+                    self.after_prolog = true;
+                    match self.options.max_depth {
+                        Some(max) if self.path.depth() > max => {
+                            let offset = self.reader.event_offset();
+                            return Err(self.error_at(Reason::MaxDepth(max), offset));
+                        }
+                        _ => (),
+                    }
+                    let space = start.attributes().flatten().find(|a| a.key == b"xml:space");
+                    match space.as_ref().map(|a| &*a.value) {
+                        Some(b"preserve") => self.path.set_preserve_space(true),
+                        Some(b"default") => self.path.set_preserve_space(false),
+                        _ => (),
+                    }
+                }
+                Event::End(_) => self.path.end(),
+                _ => self.path.next(),
+            }
+            if !self.after_prolog {
+                match &e {
+                    Event::Decl(decl) => {
+                        let result = self.prolog.set_declaration(decl);
+                        result.map_err(|err| self.fix_position(err.into()))?;
+                    }
+                    Event::DocType(doctype) => self.prolog.set_doctype(doctype.escaped()),
+                    Event::PI(instruction) => self.prolog.push_instruction(instruction.escaped()),
+                    _ => (),
+                }
+            }
             match e {
+                Event::Text(text) if self.version() == XmlVersion::v1_1 => {
+                    let text = normalize_line_endings(self.borrow_or_copy(&text));
+                    return Ok(Event::Text(BytesText::from_escaped(text)));
+                }
+                Event::CData(text) if self.version() == XmlVersion::v1_1 => {
+                    let text = normalize_line_endings(self.borrow_or_copy(&text));
+                    return Ok(Event::CData(BytesText::from_escaped(text)));
+                }
                 Event::Start(_) | Event::End(_) | Event::Text(_) | Event::Eof | Event::CData(_) => {
-                    return Ok(e.into_owned())
+                    return Ok(e)
                 }
-                _ => buf.clear(),
+                _ => (),
             }
         }
     }
 
-    fn next_start(&mut self, buf: &mut Vec<u8>) -> Result<Option<BytesStart<'static>>, Error> {
+    fn next_start(&mut self) -> Result<Option<BytesStart<'de>>, Error> {
         loop {
-            let e = self.next(buf)?;
+            let e = self.next()?;
             match e {
                 Event::Start(e) => return Ok(Some(e)),
                 Event::End(_) => return Err(self.error(Reason::End)),
                 Event::Eof => return Ok(None),
-                _ => buf.clear(), // ignore texts
+                _ => (), // ignore texts
             }
         }
     }
@@ -207,15 +686,30 @@ impl<R: BufRead> Deserializer<R> {
     /// |`<tag ...>text</tag>`|`text`     |Complete tag consumed       |
     /// |`<tag/>`             |empty slice|Virtual end tag not consumed|
     /// |`</tag>`             |empty slice|Not consumed                |
-    fn next_text(&mut self) -> Result<BytesText<'static>, Error> {
-        match self.next(&mut Vec::new())? {
+    ///
+    /// Errors raised for the text before anything else is read are located at the text.
+    fn next_text(&mut self) -> Result<BytesText<'de>, Error> {
+        let text = self.read_text()?;
+        self.text_end = Some(self.reader.reader().buffer_position());
+        Ok(text)
+    }
+
+    fn read_text(&mut self) -> Result<BytesText<'de>, Error> {
+        let keep = std::mem::take(&mut self.keep_text);
+        let text = self.next()?;
+        self.text_offset = self.reader.event_offset();
+        match text {
+            Event::Text(e) if !keep => Ok(self.trimmed_text(&e)),
             Event::Text(e) | Event::CData(e) => Ok(e),
             Event::Eof => Err(self.error(Reason::Eof)),
             Event::Start(e) => {
+                self.check_attributes(&e)?;
                 // allow one nested level
-                let inner = self.next(&mut Vec::new())?;
+                let inner = self.next()?;
+                self.text_offset = self.reader.event_offset();
                 let t = match inner {
-                    Event::Text(t) | Event::CData(t) => t,
+                    Event::Text(t) => self.trimmed_text(&t),
+                    Event::CData(t) => t,
                     Event::Start(_) => return Err(self.error(Reason::Start)),
                     Event::End(end) if end.name() == e.name() => {
                         return Ok(BytesText::from_escaped(&[] as &[u8]));
@@ -235,37 +729,271 @@ impl<R: BufRead> Deserializer<R> {
         }
     }
 
+    /// Trims `text` at `text_offset` like [`text_content`](Self::text_content)
+    fn trimmed_text(&mut self, text: &[u8]) -> BytesText<'de> {
+        let (content, offset) = self.text_content(text, self.text_offset);
+        self.text_offset = offset;
+        BytesText::from_escaped(content)
+    }
+
+    /// Reports the attributes of `start`, which is deserialized as value without attributes
+    pub(crate) fn check_attributes(&mut self, start: &BytesStart) -> Result<(), Error> {
+        let ignored = self.ignored_attribute.take();
+        if self.options.unknown_fields == UnknownFields::Ignore && self.skip_hook.is_none() {
+            return Ok(());
+        }
+        let start_offset = self.reader.event_offset();
+        for a in start.attributes() {
+            let a = a.map_err(|err| self.fix_position(err.into()))?;
+            let name = match self.attribute_name(a.key) {
+                Some(name) if !is_schema_attribute(&name) && Some(&*name) != ignored.as_deref() => {
+                    name
+                }
+                _ => continue,
+            };
+            let offset = start_offset + (a.value.as_ptr() as usize - start.as_ptr() as usize);
+            let key = [&b"@"[..], &name].concat();
+            if self.skip_hook.is_some() {
+                // the value ends before the closing quote
+                let start = start_offset + (a.key.as_ptr() as usize - start.as_ptr() as usize);
+                let path = self.path.attribute(&key);
+                self.skipped(&path, &key, start..offset + a.value.len() + 1);
+            }
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let err = Error::new(Reason::UnknownAttribute(name), offset)
+                .fix_path(|| self.path.attribute(&key));
+            self.unknown(err)?;
+        }
+        Ok(())
+    }
+
+    /// Reports an unknown element or attribute as error or warning depending on the options
+    pub(crate) fn unknown(&mut self, err: Error) -> Result<(), Error> {
+        let err = self.fix_position(err);
+        match self.options.unknown_fields {
+            UnknownFields::Ignore => Ok(()),
+            UnknownFields::Warn => {
+                self.warnings.push(err);
+                Ok(())
+            }
+            UnknownFields::Error => Err(err),
+        }
+    }
+
+    /// Skips everything up to and including the end of the element `name`
+    ///
+    /// Remaining child elements are not deserialized, so they are reported as unknown elements
+    /// and to the skip hook.
     fn read_to_end(&mut self, name: &[u8]) -> Result<(), Error> {
-        let mut buf = Vec::new();
-        match self.next(&mut buf)? {
-            Event::Start(e) => self.reader.read_to_end(e.name(), &mut Vec::new())?,
-            Event::End(e) if e.name() == name => return Ok(()),
-            _ => buf.clear(),
+        let report =
+            self.options.unknown_fields != UnknownFields::Ignore || self.skip_hook.is_some();
+        self.skip_to_end(name, report)
+    }
+
+    /// Skips everything up to and including the end of the element `name`, optionally reporting
+    /// child elements
+    pub(crate) fn skip_to_end(&mut self, name: &[u8], report: bool) -> Result<(), Error> {
+        let mut depth = 0usize;
+        // the path only contains the element `name` and a peeked or reported child, an event
+        // after peeked whitespace is handled like the peeked event
+        let mut peek = self.peek.take();
+        if self.after_peek.is_some() {
+            peek = self.after_peek.take();
+        }
+        let mut child_in_path = matches!(peek, Some(Event::Start(_)));
+        // path, name and start offset of a reported child for the skip hook
+        let mut skipped = None;
+        loop {
+            let fresh = peek.is_none();
+            let event = match peek.take() {
+                Some(event) => event,
+                None => {
+                    let namespace_buffer = if self.options.resolve_namespaces {
+                        Some(&mut self.namespace_buffer)
+                    } else {
+                        None
+                    };
+                    match self.reader.read_event(namespace_buffer) {
+                        Ok(event) => event,
+                        Err(err) => return Err(self.fix_position(err)),
+                    }
+                }
+            };
+            match event {
+                Event::Start(e) => {
+                    if depth == 0 && report {
+                        let e = e.into_owned();
+                        if fresh {
+                            self.path.start(e.name());
+                            child_in_path = true;
+                        }
+                        let key = self.element_key(&e).into_owned();
+                        let offset = self.reader.event_offset();
+                        if self.skip_hook.is_some() {
+                            // the offset of a start element is after `<`
+                            skipped = Some((self.path.to_string(), key.clone(), offset - 1));
+                        }
+                        let name = String::from_utf8_lossy(&key).into_owned();
+                        self.unknown(Error::new(Reason::UnknownElement(name), offset))?;
+                    }
+                    depth += 1;
+                }
+                Event::End(e) if depth == 0 => {
+                    if fresh {
+                        self.path.end();
+                    }
+                    let matches = e.name() == name || !self.options.check_end_names;
+                    return if matches {
+                        Ok(())
+                    } else {
+                        Err(self.error(Reason::End))
+                    };
+                }
+                Event::End(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some((path, name, start)) = skipped.take() {
+                            let end = self.reader.reader().buffer_position();
+                            self.skipped(&path, &name, start..end);
+                        }
+                        if child_in_path {
+                            self.path.end();
+                            child_in_path = false;
+                        }
+                    }
+                }
+                Event::Eof => return Err(self.error(Reason::Eof)),
+                _ => (),
+            }
         }
-        Ok(self.reader.read_to_end(name, &mut buf)?)
     }
 
-    pub(crate) fn decode_utf8<'a>(
-        &self,
-        v: &'a [u8],
-        reason: Utf8Error,
-    ) -> crate::error::Result<&'a str> {
-        from_utf8(v).map_err(|err| {
-            let offset = self.reader.buffer_position() - v.len() + err.valid_up_to();
-            Error::new(Reason::Xml(XmlError::Utf8(reason)), offset)
-        })
+    /// Calls the hook for the skipped element or attribute `name` at `path`
+    fn skipped(&mut self, path: &str, name: &[u8], range: Range<usize>) {
+        if let Some(hook) = &mut self.skip_hook {
+            let name = String::from_utf8_lossy(name);
+            hook(&Skipped {
+                path,
+                name: &name,
+                range,
+            });
+        }
+    }
+
+    /// Deserializes a nested element without attributes and only text as string, otherwise as
+    /// map
+    ///
+    /// Untyped values, like the content of flattened fields, get a string for `<a>text</a>`.
+    /// The root element is always a map.
+    fn deserialize_element<V: de::Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, Error> {
+        let start = match self.next_start()? {
+            Some(start) => start,
+            None => return Err(self.error(Reason::Start)),
+        };
+        let mut text = None;
+        let has_attributes = start.attributes().any(|a| {
+            a.map_or(true, |a| {
+                self.attribute_name(a.key).map_or(false, |name| {
+                    Some(&*name) != self.ignored_attribute.as_deref()
+                })
+            })
+        });
+        if !has_attributes && self.path.depth() > 1 {
+            if self.trims_text() {
+                self.skip_whitespace()?;
+            }
+            if let Some(Event::Text(_)) = self.peek()? {
+                let offset = self.reader.event_offset();
+                let (content, offset) = match self.next()? {
+                    Event::Text(content) => self.text_content(&content, offset),
+                    _ => unreachable!(),
+                };
+                if let Some(Event::End(_)) = self.peek()? {
+                    let value = de::Deserializer::deserialize_str(
+                        self.escaped(content, true, offset),
+                        visitor,
+                    )
+                    .map_err(|err| self.fix_position(err))?;
+                    self.read_to_end(start.name())?;
+                    return Ok(value);
+                }
+                text = Some((content, offset));
+            }
+        }
+
+        let mut map = map::MapAccess::new(self, &start, None)?;
+        if let Some((content, offset)) = text {
+            map = map.with_text(content, offset);
+        }
+        let value = visitor
+            .visit_map(map)
+            .map_err(|err| self.fix_position(err))?;
+        self.read_to_end(start.name())?;
+        Ok(value)
+    }
+
+    /// Deserializes an element as structure with `fields` or as map without
+    fn deserialize_fields<V: de::Visitor<'de>>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value_fields = None;
+        if let Some(e) = self.next_start()? {
+            let map = map::MapAccess::new(self, &e, fields)?;
+            let value = visitor
+                .visit_map(map)
+                .map_err(|err| self.fix_position(err))?;
+            self.read_to_end(e.name())?;
+            Ok(value)
+        } else {
+            Err(self.error(Reason::Start))
+        }
     }
 
     pub(crate) fn error(&self, reason: Reason) -> Error {
-        Error::new(reason, self.reader.buffer_position())
+        self.error_at(reason, self.reader.reader().buffer_position())
     }
 
     pub(crate) fn peek_error(&self, reason: Reason) -> Error {
-        Error::new(reason, self.reader.buffer_position())
+        self.error_at(reason, self.reader.reader().buffer_position())
+    }
+
+    fn error_at(&self, reason: Reason, offset: usize) -> Error {
+        self.fix_position(Error::new(reason, offset))
     }
 
+    /// Locate an error
+    ///
+    /// Errors without offset are located at the last text, if nothing was read after it, e.g.
+    /// errors of `Deserialize` implementations, or at the current position.
     pub(crate) fn fix_position(&self, err: Error) -> Error {
-        err.fix_position(|reason| self.error(reason))
+        err.fix_position(
+            || {
+                let position = self.reader.reader().buffer_position();
+                match self.text_end {
+                    Some(end) if end == position => self.text_offset,
+                    _ => position,
+                }
+            },
+            |offset| self.reader.location(offset),
+        )
+        .fix_path(|| self.path.to_string())
+    }
+
+    /// Deserializer for an attribute value or a name at `offset`
+    pub(crate) fn escaped(
+        &self,
+        value: Cow<'de, [u8]>,
+        escaped: bool,
+        offset: usize,
+    ) -> EscapedDeserializer<'de> {
+        let value = if escaped && self.version() == XmlVersion::v1_1 {
+            normalize_line_endings(value)
+        } else {
+            value
+        };
+        EscapedDeserializer::new(value, escaped, offset, self.options.clone())
     }
 }
 
@@ -275,15 +1003,18 @@ macro_rules! deserialize_type {
             let txt = self.next_text()?;
             let value = self
                 .reader
-                .decode(&*txt)?
+                .reader()
+                .decode(&*txt)
+                .map_err(|err| self.error_at(Reason::Xml(err), self.text_offset))?
                 .parse::<$ty>()
-                .at_offset(self.reader.buffer_position())?;
-            visitor.$visit(value)
+                .at_offset(self.text_offset)
+                .map_err(|err| self.fix_position(err))?;
+            visitor.$visit(value).map_err(|err| self.fix_position(err))
         }
     };
 }
 
-impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
+impl<'de, R: XmlRead<'de>> de::Deserializer<'de> for &mut Deserializer<'de, R> {
     type Error = Error;
 
     fn deserialize_struct<V: de::Visitor<'de>>(
@@ -292,18 +1023,7 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Error> {
-        if let Some(e) = self.next_start(&mut Vec::new())? {
-            self.has_value_field = fields.contains(&INNER_VALUE);
-            let map = map::MapAccess::new(self, &e)?;
-            let value = visitor
-                .visit_map(map)
-                .map_err(|err| self.fix_position(err))?;
-            self.has_value_field = false;
-            self.read_to_end(&e.name())?;
-            Ok(value)
-        } else {
-            Err(self.error(Reason::Start))
-        }
+        self.deserialize_fields(Some(fields), visitor)
     }
 
     deserialize_type!(deserialize_i8 => i8, visit_i8);
@@ -317,22 +1037,35 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
     deserialize_type!(deserialize_f32 => f32, visit_f32);
     deserialize_type!(deserialize_f64 => f64, visit_f64);
 
-    serde_if_integer128! {
-        deserialize_type!(deserialize_i128 => i128, visit_i128);
-        deserialize_type!(deserialize_u128 => u128, visit_u128);
-    }
+    deserialize_type!(deserialize_i128 => i128, visit_i128);
+    deserialize_type!(deserialize_u128 => u128, visit_u128);
 
     fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
         let txt = self.next_text()?;
-        match txt.as_ref() {
-            b"true" | b"1" | b"yes" => visitor.visit_bool(true),
-            b"false" | b"0" | b"no" => visitor.visit_bool(false),
-            e => Err(self.error(Reason::InvalidBoolean(self.reader.decode(e)?.into()))),
+        match self.options.booleans.parse(&txt) {
+            Some(value) => visitor.visit_bool(value),
+            None => {
+                let e = txt.as_ref();
+                let reason = match self.reader.reader().decode(e) {
+                    Ok(e) => Reason::InvalidBoolean(e.into()),
+                    Err(err) => Reason::Xml(err),
+                };
+                Err(self.error_at(reason, self.text_offset))
+            }
         }
+        .map_err(|err| self.fix_position(err))
     }
 
     fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        let value = self.next_text()?.unescape_and_decode(&self.reader)?;
+        let value = self.next_text()?;
+        let error = |err| self.error_at(Reason::Xml(err), self.text_offset);
+        let value = self.options.unescape(&value).map_err(error)?;
+        let value = self
+            .reader
+            .reader()
+            .decode(&value)
+            .map_err(error)?
+            .to_string();
         visitor
             .visit_string(value)
             .map_err(|err| self.fix_position(err))
@@ -343,22 +1076,57 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
     }
 
     fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        self.deserialize_string(visitor)
+        let text = self.next_text()?;
+        let error = |err| self.error_at(Reason::Xml(err), self.text_offset);
+        let value = match self.reader.borrow(text.escaped()) {
+            Some(escaped) => match self.options.unescape(escaped).map_err(error)? {
+                Cow::Borrowed(value) => {
+                    let value = self.reader.reader().decode(value).map_err(error)?;
+                    return visitor
+                        .visit_borrowed_str(value)
+                        .map_err(|err| self.fix_position(err));
+                }
+                Cow::Owned(value) => self
+                    .reader
+                    .reader()
+                    .decode(&value)
+                    .map_err(error)?
+                    .to_string(),
+            },
+            None => {
+                let value = self.options.unescape(&text).map_err(error)?;
+                self.reader
+                    .reader()
+                    .decode(&value)
+                    .map_err(error)?
+                    .to_string()
+            }
+        };
+        visitor
+            .visit_string(value)
+            .map_err(|err| self.fix_position(err))
     }
 
     fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        // TODO: does it make sense? to get escaped content -> base64 or hex / error?
         let text = self.next_text()?;
-        let value = text.escaped();
+        let text = self
+            .options
+            .unescape(&text)
+            .map_err(|err| self.error_at(Reason::Xml(err), self.text_offset))?;
+        let value = self
+            .options
+            .binary_encoding
+            .decode(&text)
+            .map_err(|reason| self.error_at(reason, self.text_offset))?;
         visitor
-            .visit_bytes(value)
+            .visit_byte_buf(value)
             .map_err(|err| self.fix_position(err))
     }
 
     fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        let mut buf = Vec::new();
-        match self.next(&mut buf)? {
+        match self.next()? {
             Event::Start(s) => {
+                self.check_attributes(&s)?;
                 self.read_to_end(s.name())?;
                 visitor.visit_unit().map_err(|err| self.fix_position(err))
             }
@@ -376,9 +1144,15 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
 
     fn deserialize_newtype_struct<V: de::Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Error> {
+        if name == MAP_ENTRIES {
+            self.map_entries = true;
+            let value = visitor.visit_newtype_struct(&mut *self);
+            self.map_entries = false;
+            return value;
+        }
         // TODO: can be optimized
         self.deserialize_tuple(1, visitor)
     }
@@ -405,23 +1179,58 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
     fn deserialize_enum<V: de::Visitor<'de>>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Error> {
+        self.value_fields = None;
+        let field = std::mem::take(&mut self.field_element);
         let value = visitor
-            .visit_enum(var::EnumAccess::new(self))
+            .visit_enum(var::EnumAccess::new(self, variants, field))
             .map_err(|err| self.fix_position(err))?;
         Ok(value)
     }
 
     fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // a sequence in a newtype variant is the content of the variant element, not a list of
+        // variant elements
+        if let Some(offset) = self.variant_start.take() {
+            if let Some(Event::Start(_)) = self.peek()? {
+                if self.reader.event_offset() == offset {
+                    let e = self
+                        .next_start()?
+                        .ok_or_else(|| self.error(Reason::Start))?;
+                    self.check_attributes(&e)?;
+                    let value = visitor
+                        .visit_seq(seq::SeqAccess::content(self))
+                        .map_err(|err| self.fix_position(err))?;
+                    self.read_to_end(e.name())?;
+                    return Ok(value);
+                }
+            }
+        }
         visitor
             .visit_seq(seq::SeqAccess::new(self, None)?)
             .map_err(|err| self.fix_position(err))
     }
 
     fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        self.deserialize_struct("", &[], visitor)
+        if !std::mem::take(&mut self.map_entries) {
+            return self.deserialize_fields(None, visitor);
+        }
+        let (element, key) = match &self.options.entry_names {
+            Some((element, key)) => (element.clone(), key.clone()),
+            None => (ENTRY.as_bytes().to_vec(), ENTRY_KEY.as_bytes().to_vec()),
+        };
+        if let Some(e) = self.next_start()? {
+            let map = map::EntryAccess::new(self, element, key);
+            let value = visitor
+                .visit_map(map)
+                .map_err(|err| self.fix_position(err))?;
+            self.read_to_end(e.name())?;
+            Ok(value)
+        } else {
+            Err(self.error(Reason::Start))
+        }
     }
 
     fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
@@ -433,12 +1242,27 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
     }
 
     fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        self.deserialize_string(visitor)
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
-        match self.next(&mut Vec::new())? {
-            Event::Start(e) => self.read_to_end(e.name())?,
+        match self.next()? {
+            Event::Start(e) => {
+                // the offset of a start element is after `<`
+                let skipped = self.skip_hook.is_some().then(|| {
+                    let start = self.reader.event_offset().saturating_sub(1);
+                    (
+                        self.path.to_string(),
+                        self.element_key(&e).into_owned(),
+                        start,
+                    )
+                });
+                self.skip_to_end(e.name(), false)?;
+                if let Some((path, name, start)) = skipped {
+                    let end = self.reader.reader().buffer_position();
+                    self.skipped(&path, &name, start..end);
+                }
+            }
             Event::End(_) => return Err(self.error(Reason::End)),
             _ => (),
         }
@@ -448,9 +1272,9 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<R> {
     fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
         if let Some(event) = self.peek()? {
             match event {
-                Event::Start(_) => self.deserialize_map(visitor),
+                Event::Start(_) => self.deserialize_element(visitor),
                 Event::End(_) => self.deserialize_unit(visitor),
-                _ => self.deserialize_string(visitor),
+                _ => self.deserialize_str(visitor),
             }
         } else {
             Err(self.peek_error(Reason::Eof))
@@ -659,11 +1483,11 @@ mod tests {
                     }
                 }
 
-                Ok(d.deserialize_byte_buf(ItemVisitor)?)
+                d.deserialize_byte_buf(ItemVisitor)
             }
         }
 
-        let s = r#"<item>bytes</item>"#;
+        let s = r#"<item>Ynl0ZXM=</item>"#;
         let item: Item = from_reader(s.as_bytes()).unwrap();
 
         assert_eq!(
@@ -861,7 +1685,6 @@ mod tests {
         use super::*;
 
         #[test]
-        #[ignore = "Prime cause: deserialize_any under the hood + https://github.com/serde-rs/serde/issues/1183"]
         fn elements() {
             #[derive(Debug, Deserialize, PartialEq)]
             struct Struct {
@@ -1082,7 +1905,6 @@ mod tests {
                 use super::*;
 
                 #[test]
-                #[ignore = "Prime cause: deserialize_any under the hood + https://github.com/serde-rs/serde/issues/1183"]
                 fn elements() {
                     let data: Node =
                         from_str(r#"<Flatten><float>42</float><string>answer</string></Flatten>"#)
@@ -1507,7 +2329,6 @@ mod tests {
                 use super::*;
 
                 #[test]
-                #[ignore = "Prime cause: deserialize_any under the hood + https://github.com/serde-rs/serde/issues/1183"]
                 fn elements() {
                     let data: Node = from_str(
                         r#"<root><tag>Flatten</tag><content><float>42</float><string>answer</string></content></root>"#
@@ -1660,7 +2481,6 @@ mod tests {
                 use super::*;
 
                 #[test]
-                #[ignore = "Prime cause: deserialize_any under the hood + https://github.com/serde-rs/serde/issues/1183"]
                 fn elements() {
                     let data: Node = from_str(
                         r#"<root><string>answer</string><nested><float>42</float></nested></root>"#,
@@ -1693,7 +2513,6 @@ mod tests {
                 use super::*;
 
                 #[test]
-                #[ignore = "Prime cause: deserialize_any under the hood + https://github.com/serde-rs/serde/issues/1183"]
                 fn elements() {
                     let data: Node =
                         from_str(r#"<root><float>42</float><string2>answer</string2></root>"#)
@@ -1722,4 +2541,1703 @@ mod tests {
             }
         }
     }
+
+    mod borrowed {
+        use super::*;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Borrowed<'a> {
+            #[serde(rename = "@id")]
+            id: &'a str,
+            name: &'a str,
+            #[serde(borrow)]
+            description: Cow<'a, str>,
+            #[serde(rename = "@note", borrow)]
+            note: Cow<'a, str>,
+        }
+
+        #[test]
+        fn text_and_attributes() {
+            let xml = r#"<?xml version="1.0"?>
+                <!-- comment -->
+                <item id="1" note="a &amp; b">
+                    <name>  name  </name>
+                    <empty/>
+                    <description><![CDATA[description]]></description>
+                </item>"#;
+            let item: Borrowed = from_str(xml).unwrap();
+
+            assert_eq!(item.id, "1");
+            assert_eq!(item.name, "name");
+            assert!(matches!(item.description, Cow::Borrowed("description")));
+            assert!(matches!(&item.note, Cow::Owned(note) if note == "a & b"));
+        }
+
+        #[test]
+        fn events() {
+            let xml = r#"<a x="1"><b/><c y="2"/><d><![CDATA[<e>]]></d ></a>text"#;
+            let mut reader = SliceReader::new(xml.as_bytes());
+            let mut borrowed = Vec::new();
+            loop {
+                let event = reader.next(None).unwrap();
+                let content = match &event {
+                    Event::Start(e) => e.name(),
+                    Event::End(e) => e.name(),
+                    Event::Text(e) | Event::CData(e) => e.escaped(),
+                    Event::Eof => break,
+                    _ => continue,
+                };
+                let raw = reader.borrow(content).is_some();
+                borrowed.push((String::from_utf8(content.to_vec()).unwrap(), raw));
+            }
+            let expected = [
+                ("a", true),
+                ("b", true),
+                ("b", true),
+                ("c", true),
+                ("c", true),
+                ("d", true),
+                ("&lt;e&gt;", false),
+                ("d", true),
+                ("a", true),
+                ("text", true),
+            ];
+            let expected: Vec<_> = expected.iter().map(|(c, b)| (c.to_string(), *b)).collect();
+            assert_eq!(borrowed, expected);
+        }
+
+        #[test]
+        fn escaped_str() {
+            #[derive(Debug, Deserialize)]
+            struct Item<'a> {
+                #[allow(dead_code)]
+                name: &'a str,
+            }
+
+            assert!(from_str::<Item>("<item><name>a &amp; b</name></item>").is_err());
+        }
+
+        #[test]
+        fn namespaces() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Item<'a> {
+                #[serde(rename = "{urn:a}name")]
+                name: &'a str,
+            }
+
+            let mut de =
+                Deserializer::from_str(r#"<a:item xmlns:a="urn:a"><a:name>n</a:name></a:item>"#)
+                    .with_options(DeserializerOptions::new().resolve_namespaces(true));
+            assert_eq!(Item::deserialize(&mut de).unwrap(), Item { name: "n" });
+        }
+
+        #[test]
+        fn reader_copies() {
+            let xml = r#"<item id="1" note="n"><name>n</name><description>d</description></item>"#;
+            let mut de = Deserializer::from_reader(xml.as_bytes());
+            assert!(Borrowed::deserialize(&mut de).is_err());
+        }
+    }
+
+    mod iter_path {
+        use super::*;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Point {
+            #[serde(rename = "@lat")]
+            lat: f64,
+            name: Option<String>,
+        }
+
+        const GPX: &str = r#"<?xml version="1.0"?>
+            <gpx>
+                <wpt lat="0"><trkseg><trkpt lat="-1"/></trkseg></wpt>
+                <trk>
+                    <name>track</name>
+                    <trkseg>
+                        <trkpt lat="1"><name>first</name></trkpt>
+                        <other/>
+                        <trkpt lat="2"/>
+                    </trkseg>
+                    <trkseg><trkpt lat="3"/></trkseg>
+                </trk>
+                <trk><trkseg/></trk>
+            </gpx>"#;
+
+        #[test]
+        fn items() {
+            let mut de = Deserializer::from_reader(GPX.as_bytes());
+            let points: Vec<Point> = de
+                .iter_path("gpx/trk/trkseg/trkpt")
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(
+                points,
+                vec![
+                    Point {
+                        lat: 1.0,
+                        name: Some("first".into())
+                    },
+                    Point {
+                        lat: 2.0,
+                        name: None
+                    },
+                    Point {
+                        lat: 3.0,
+                        name: None
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn primitives() {
+            let mut de = Deserializer::from_str(GPX);
+            let names: Vec<&str> = de
+                .iter_path("/gpx/trk/name")
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(names, vec!["track"]);
+        }
+
+        #[test]
+        fn no_match() {
+            let mut de = Deserializer::from_str(GPX);
+            assert_eq!(de.iter_path::<Point>("trk/trkseg/trkpt").count(), 0);
+        }
+
+        #[test]
+        fn stops_after_error() {
+            let mut de = Deserializer::from_str(r#"<a><b lat="1"/><b lat="x"/><b lat="3"/></a>"#);
+            let points: Vec<Result<Point, Error>> = de.iter_path("a/b").collect();
+            assert_eq!(points.len(), 2);
+            assert!(points[0].is_ok());
+            assert!(points[1].is_err());
+        }
+    }
+
+    mod namespaces {
+        use super::*;
+
+        const SOAP: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Envelope {
+            #[serde(rename = "soap:Header")]
+            header: Option<String>,
+            #[serde(rename = "soap:Body")]
+            body: Body,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Body {
+            #[serde(rename = "@soap:encodingStyle")]
+            encoding_style: String,
+            #[serde(rename = "@id")]
+            id: String,
+            #[serde(rename = "{urn:example}item")]
+            item: String,
+        }
+
+        fn from_str_with_soap<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+            let mut de = Deserializer::from_reader(s.as_bytes())
+                .with_options(DeserializerOptions::new().with_namespace("soap", SOAP));
+            T::deserialize(&mut de)
+        }
+
+        #[test]
+        fn independent_of_document_prefix() {
+            let expected = Envelope {
+                header: Some("header".into()),
+                body: Body {
+                    encoding_style: "style".into(),
+                    id: "1".into(),
+                    item: "item".into(),
+                },
+            };
+
+            let data: Envelope = from_str_with_soap(
+                r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+                    <soap:Header>header</soap:Header>
+                    <soap:Body soap:encodingStyle="style" id="1" xmlns:ex="urn:example">
+                        <ex:item>item</ex:item>
+                    </soap:Body>
+                </soap:Envelope>"#,
+            )
+            .unwrap();
+            assert_eq!(data, expected);
+
+            let data: Envelope = from_str_with_soap(
+                r#"<env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope">
+                    <env:Header>header</env:Header>
+                    <Body xmlns="http://www.w3.org/2003/05/soap-envelope" env:encodingStyle="style" id="1">
+                        <item xmlns="urn:example">item</item>
+                    </Body>
+                </env:Envelope>"#,
+            )
+            .unwrap();
+            assert_eq!(data, expected);
+        }
+
+        #[test]
+        fn other_namespace_does_not_match() {
+            let data: Result<Envelope, Error> = from_str_with_soap(
+                r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+                    <soap:Body/>
+                </soap:Envelope>"#,
+            );
+            assert!(data.is_err());
+        }
+
+        #[test]
+        fn declarations_are_no_attributes() {
+            let mut de = Deserializer::from_reader(
+                r#"<root xmlns="urn:a" xmlns:b="urn:b" b:attr="1" xml:lang="en"/>"#.as_bytes(),
+            )
+            .with_options(DeserializerOptions::new().resolve_namespaces(true));
+            let data = serde_value::Value::deserialize(&mut de).unwrap();
+
+            assert_eq!(
+                data,
+                serde_value::Value::Map(
+                    vec![
+                        (
+                            serde_value::Value::String("@{urn:b}attr".into()),
+                            serde_value::Value::String("1".into())
+                        ),
+                        (
+                            serde_value::Value::String("@xml:lang".into()),
+                            serde_value::Value::String("en".into())
+                        ),
+                    ]
+                    .into_iter()
+                    .collect()
+                )
+            );
+        }
+
+        #[test]
+        fn scope_ends_with_skipped_element() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Root {
+                #[serde(rename = "{urn:b}item")]
+                item: Vec<String>,
+            }
+
+            let mut de = Deserializer::from_reader(
+                r#"<root>
+                    <x:ignored xmlns:x="urn:a"><x:item>a</x:item></x:ignored>
+                    <x:item xmlns:x="urn:b">b1</x:item>
+                    <x:item xmlns:x="urn:b">b2</x:item>
+                </root>"#
+                    .as_bytes(),
+            )
+            .with_options(DeserializerOptions::new().resolve_namespaces(true));
+            let data = Root::deserialize(&mut de).unwrap();
+            assert_eq!(
+                data,
+                Root {
+                    item: vec!["b1".into(), "b2".into()]
+                }
+            );
+        }
+    }
+
+    mod location {
+        use super::*;
+
+        #[derive(Debug, Deserialize)]
+        struct Point {
+            #[serde(rename = "@lat")]
+            _lat: f64,
+            #[serde(rename = "ele")]
+            _ele: f64,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Track {
+            #[serde(rename = "trkpt")]
+            _points: Vec<Point>,
+        }
+
+        fn location(err: Error) -> (usize, usize) {
+            (err.line(), err.column())
+        }
+
+        #[test]
+        fn text() {
+            let xml = "<trk>\n  <trkpt lat=\"1\">\n    <ele>x1</ele>\n  </trkpt>\n</trk>";
+            assert_eq!(location(from_str::<Track>(xml).unwrap_err()), (3, 10));
+            assert_eq!(
+                location(from_reader::<_, Track>(xml.as_bytes()).unwrap_err()),
+                (3, 10)
+            );
+
+            let err = from_str::<Track>(xml).unwrap_err();
+            assert!(err.to_string().ends_with(" at 3:10"), "{}", err);
+        }
+
+        #[test]
+        fn attribute() {
+            let xml = "<trk>\n<trkpt lat=\"one\"><ele>1</ele></trkpt>\n</trk>";
+            assert_eq!(location(from_str::<Track>(xml).unwrap_err()), (2, 13));
+            assert_eq!(
+                location(from_reader::<_, Track>(xml.as_bytes()).unwrap_err()),
+                (2, 13)
+            );
+        }
+
+        #[test]
+        fn custom() {
+            #[derive(Debug)]
+            struct Even;
+
+            impl<'de> Deserialize<'de> for Even {
+                fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                    match u32::deserialize(d)? {
+                        n if n % 2 == 0 => Ok(Even),
+                        _ => Err(de::Error::custom("odd number")),
+                    }
+                }
+            }
+
+            // at the value, not after the element
+            let err = from_str::<Even>("\n\n<n>3</n>").unwrap_err();
+            assert_eq!(location(err), (3, 4));
+            let err = from_str::<Even>("\n\n<n>\n  3\n</n>").unwrap_err();
+            assert_eq!(location(err), (4, 3));
+
+            #[derive(Debug, Deserialize)]
+            struct Pair {
+                #[serde(rename = "@n")]
+                _attribute: Even,
+                #[serde(rename = "n")]
+                _element: Even,
+            }
+
+            let err = from_str::<Pair>(r#"<pair n="1"><n>2</n></pair>"#).unwrap_err();
+            assert_eq!(location(err), (1, 10));
+            let err = from_str::<Pair>(r#"<pair n="2"><n>3</n></pair>"#).unwrap_err();
+            assert_eq!(location(err), (1, 16));
+        }
+
+        #[test]
+        fn borrowed_str() {
+            #[derive(Debug, Deserialize)]
+            struct Record<'a> {
+                #[serde(rename = "@id")]
+                _id: Option<&'a str>,
+                #[serde(rename = "name")]
+                _name: Option<&'a str>,
+            }
+
+            // escaped values cannot be borrowed
+            let err = from_str::<Record>("<r><name>a&amp;b</name></r>").unwrap_err();
+            assert_eq!(location(err), (1, 10));
+            let err = from_str::<Record>(r#"<r id="a&amp;b"/>"#).unwrap_err();
+            assert_eq!(location(err), (1, 8));
+        }
+
+        #[test]
+        fn syntax() {
+            let xml = "<trk>\n<trkpt></trk>";
+            assert_eq!(location(from_str::<Track>(xml).unwrap_err()).0, 2);
+            assert_eq!(
+                location(from_reader::<_, Track>(xml.as_bytes()).unwrap_err()).0,
+                2
+            );
+        }
+    }
+
+    mod path {
+        use super::*;
+
+        #[derive(Debug, Deserialize)]
+        struct Point {
+            #[serde(rename = "@lat")]
+            _lat: f64,
+            #[serde(default, rename = "ele")]
+            _ele: f64,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Segment {
+            #[serde(rename = "trkpt")]
+            _points: Vec<Point>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Track {
+            #[serde(rename = "trkseg")]
+            _segments: Vec<Segment>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Gpx {
+            #[serde(rename = "trk")]
+            _tracks: Vec<Track>,
+        }
+
+        fn path(xml: &str) -> String {
+            from_str::<Gpx>(xml)
+                .unwrap_err()
+                .path()
+                .unwrap_or_default()
+                .to_string()
+        }
+
+        #[test]
+        fn attribute() {
+            let xml = r#"<gpx>
+                <trk><trkseg><trkpt lat="1"/></trkseg></trk>
+                <trk>
+                    <trkseg><trkpt lat="1"/></trkseg>
+                    <trkseg><trkpt lat="1"/><trkpt lat="x"/></trkseg>
+                </trk>
+            </gpx>"#;
+            assert_eq!(path(xml), "gpx/trk[1]/trkseg[1]/trkpt[1]/@lat");
+
+            let err = from_str::<Gpx>(xml).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains(" in gpx/trk[1]/trkseg[1]/trkpt[1]/@lat at 5:"));
+        }
+
+        #[test]
+        fn text() {
+            let xml = r#"<gpx><trk><trkseg>
+                <trkpt lat="1"><ele>1</ele></trkpt>
+                <trkpt lat="1"><ele>x</ele></trkpt>
+            </trkseg></trk></gpx>"#;
+            assert_eq!(path(xml), "gpx/trk[0]/trkseg[0]/trkpt[1]/ele[0]");
+        }
+
+        #[test]
+        fn skipped() {
+            let xml = r#"<gpx>
+                <metadata><trk/><trk/></metadata>
+                <trk><name>a</name><trkseg><trkpt/></trkseg></trk>
+            </gpx>"#;
+            assert_eq!(path(xml), "gpx/trk[0]/trkseg[0]/trkpt[0]");
+        }
+    }
+
+    mod encoding {
+        use super::*;
+        use std::io::BufReader;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Person {
+            #[serde(rename = "@city")]
+            city: String,
+            name: String,
+        }
+
+        fn utf16(xml: &str, bom: &[u8], to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+            let mut bytes = bom.to_vec();
+            bytes.extend(xml.encode_utf16().flat_map(to_bytes));
+            bytes
+        }
+
+        fn person() -> Person {
+            Person {
+                city: "Köln".to_string(),
+                name: "Müller 𝄞".to_string(),
+            }
+        }
+
+        #[test]
+        fn declaration() {
+            let xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n\
+                <person city=\"K\xF6ln\"><name>M\xFCller &#x1D11E;</name></person>";
+            assert_eq!(from_reader::<_, Person>(&xml[..]).unwrap(), person());
+            assert_eq!(from_slice::<Person>(xml).unwrap(), person());
+            let reader = BufReader::with_capacity(3, &xml[..]);
+            assert_eq!(from_reader::<_, Person>(reader).unwrap(), person());
+        }
+
+        #[test]
+        fn byte_order_mark() {
+            let xml = r#"<?xml version="1.0"?><person city="Köln"><name>Müller 𝄞</name></person>"#;
+            let le = utf16(xml, b"\xFF\xFE", u16::to_le_bytes);
+            assert_eq!(from_slice::<Person>(&le).unwrap(), person());
+            // chunks split characters
+            let reader = BufReader::with_capacity(3, &le[..]);
+            assert_eq!(from_reader::<_, Person>(reader).unwrap(), person());
+
+            let be = utf16(xml, b"", u16::to_be_bytes);
+            assert_eq!(from_slice::<Person>(&be).unwrap(), person());
+
+            let utf8 = [&b"\xEF\xBB\xBF"[..], xml.as_bytes()].concat();
+            assert_eq!(from_slice::<Person>(&utf8).unwrap(), person());
+            assert_eq!(from_reader::<_, Person>(&utf8[..]).unwrap(), person());
+        }
+
+        #[test]
+        fn forced() {
+            let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                <person city=\"K\xF6ln\"><name>M\xFCller \x80</name></person>";
+            let mut de = Deserializer::from_reader(&xml[..]).with_encoding(Encoding::Windows1252);
+            let data = Person::deserialize(&mut de).unwrap();
+            assert_eq!(data.name, "Müller €");
+
+            assert!(from_reader::<_, Person>(&xml[..]).unwrap_err().is_syntax());
+        }
+
+        #[test]
+        fn unsupported() {
+            let xml = b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?><person/>";
+            let err = from_reader::<_, Person>(&xml[..]).unwrap_err();
+            assert!(err.is_syntax());
+            assert!(err.to_string().contains("EBCDIC"), "{}", err);
+
+            let err = from_slice::<Person>(b"\xFF\xFE<\0a").unwrap_err();
+            assert!(err.is_syntax());
+        }
+
+        #[test]
+        fn string_ignores_declaration() {
+            let xml = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+                <person city="Köln"><name>Müller 𝄞</name></person>"#;
+            assert_eq!(from_str::<Person>(xml).unwrap(), person());
+            let options = DeserializerOptions::new();
+            assert_eq!(options.from_str::<Person>(xml).unwrap(), person());
+
+            let xml =
+                r#"<?xml version="1.0" encoding="EBCDIC"?><person city="Köln"><name/></person>"#;
+            assert_eq!(from_str::<Person>(xml).unwrap().city, "Köln");
+        }
+    }
+
+    mod prolog {
+        use super::*;
+        use crate::prolog::{DocType, ProcessingInstruction};
+        use crate::XmlVersion;
+
+        #[test]
+        fn prolog() {
+            let xml = r#"<?xml version="1.1" encoding="UTF-8" standalone="no"?>
+                <!-- comment -->
+                <?xml-stylesheet href="style.css"?>
+                <!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "xhtml1-strict.dtd">
+                <html><?php echo 1; ?></html>"#;
+            let mut de = Deserializer::from_reader(xml.as_bytes());
+            assert_eq!(de.prolog().version(), None);
+            serde_value::Value::deserialize(&mut de).unwrap();
+
+            let prolog = de.prolog();
+            assert_eq!(prolog.version(), Some(XmlVersion::v1_1));
+            assert_eq!(prolog.encoding(), Some("UTF-8"));
+            assert_eq!(prolog.standalone(), Some(false));
+            assert_eq!(
+                prolog.doctype(),
+                Some(
+                    &DocType::new("html")
+                        .with_public_id("-//W3C//DTD XHTML 1.0 Strict//EN", "xhtml1-strict.dtd")
+                )
+            );
+            assert_eq!(
+                prolog.instructions(),
+                &[ProcessingInstruction::new(
+                    "xml-stylesheet",
+                    "href=\"style.css\""
+                )]
+            );
+        }
+
+        #[test]
+        fn without_declaration() {
+            let mut de = Deserializer::from_str("<root/>");
+            <()>::deserialize(&mut de).unwrap();
+            assert_eq!(de.prolog(), &Prolog::new());
+        }
+    }
+
+    mod version {
+        use super::*;
+        use crate::XmlVersion;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Note {
+            #[serde(rename = "@title")]
+            title: String,
+            #[serde(rename = "$value")]
+            text: String,
+        }
+
+        #[test]
+        fn xml_1_1() {
+            let xml = "<?xml version=\"1.1\"?><note title=\"a\u{85}b\">c\r\u{85}d\u{2028}&#x1;&#x85;</note>";
+            let mut de = Deserializer::from_str(xml);
+            let note = Note::deserialize(&mut de).unwrap();
+            assert_eq!(de.version(), XmlVersion::v1_1);
+            assert_eq!(
+                note,
+                Note {
+                    title: "a\nb".into(),
+                    text: "c\nd\n\u{1}\u{85}".into()
+                }
+            );
+
+            // extra name characters
+            let xml = "<?xml version=\"1.1\"?><n\u{2070}>1</n\u{2070}>";
+            let mut de = Deserializer::from_reader(xml.as_bytes());
+            assert_eq!(u32::deserialize(&mut de).unwrap(), 1);
+        }
+
+        #[test]
+        fn xml_1_0() {
+            let xml = "<note title=\"a\u{85}b\">c\u{2028}d</note>";
+            let mut de = Deserializer::from_str(xml);
+            let note = Note::deserialize(&mut de).unwrap();
+            assert_eq!(de.version(), XmlVersion::v1_0);
+            assert_eq!(note.title, "a\u{85}b");
+            assert_eq!(note.text, "c\u{2028}d");
+        }
+    }
+
+    mod map_entries {
+        use std::collections::BTreeMap;
+
+        use serde::Serialize;
+
+        use super::*;
+        use crate::{entries, Serializer, Writer};
+
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Setting {
+            #[serde(rename = "@type")]
+            kind: String,
+            #[serde(rename = "$value")]
+            value: String,
+        }
+
+        fn settings() -> BTreeMap<String, Setting> {
+            let setting = |kind: &str, value: &str| Setting {
+                kind: kind.into(),
+                value: value.into(),
+            };
+            vec![
+                ("max size".to_string(), setting("int", "10")),
+                ("<name>".to_string(), setting("string", "a")),
+            ]
+            .into_iter()
+            .collect()
+        }
+
+        #[test]
+        fn roundtrip() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new_with_root(&mut writer, Some("settings"))
+                .with_entry_names("add", "key");
+            entries::serialize(&settings(), &mut ser).unwrap();
+
+            let mut de = Deserializer::from_slice(&buffer)
+                .with_options(DeserializerOptions::new().with_entry_names("add", "key"));
+            let map: BTreeMap<_, _> = entries::deserialize(&mut de).unwrap();
+            assert_eq!(map, settings());
+        }
+
+        #[test]
+        fn skipped() {
+            let xml = r#"<settings version="2">
+                <comment>ignored</comment>
+                text
+                <add type="int" key="max size">10</add>
+                <add key="&lt;name&gt;" type="string">a</add>
+            </settings>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_entry_names("add", "key"));
+            let map: BTreeMap<_, _> = entries::deserialize(&mut de).unwrap();
+            assert_eq!(map, settings());
+        }
+
+        #[test]
+        fn unknown_elements() {
+            let xml = r#"<settings>
+                <comment>ignored</comment>
+                <add type="int" key="max size">10</add>
+                <add key="&lt;name&gt;" type="string">a</add>
+            </settings>"#;
+            let options = DeserializerOptions::new()
+                .with_unknown_fields(UnknownFields::Error)
+                .with_entry_names("add", "key");
+            let mut de = Deserializer::from_str(xml).with_options(options);
+            let err = entries::deserialize::<BTreeMap<String, Setting>, _>(&mut de).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::Unknown);
+            assert_eq!(
+                err.to_string(),
+                "Unknown element `comment` in settings/comment[0] at 2:18"
+            );
+
+            let mut skipped = Vec::new();
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_entry_names("add", "key"))
+                .on_skipped(|content| skipped.push(content.name().to_string()));
+            let map: BTreeMap<_, _> = entries::deserialize(&mut de).unwrap();
+            drop(de);
+            assert_eq!(map, settings());
+            assert_eq!(skipped, vec!["comment"]);
+        }
+
+        #[test]
+        fn key_attribute_of_value() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Limit {
+                #[serde(rename = "@max")]
+                max: u32,
+            }
+
+            // the key is no unknown attribute of the value
+            let xml = r#"<limits><entry key="a" max="1"/><entry max="2" key="b"/></limits>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_unknown_fields(UnknownFields::Error));
+            let map: BTreeMap<String, Limit> = entries::deserialize(&mut de).unwrap();
+            assert_eq!(map["a"], Limit { max: 1 });
+            assert_eq!(map["b"], Limit { max: 2 });
+            let xml = r#"<r><entry key="a">x</entry></r>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_unknown_fields(UnknownFields::Error));
+            let map: BTreeMap<String, String> = entries::deserialize(&mut de).unwrap();
+            assert_eq!(map["a"], "x");
+
+            // offsets of other attributes are in the input
+            let xml = r#"<limits><entry key="a" max="x"/></limits>"#;
+            let mut de = Deserializer::from_str(xml);
+            let err = entries::deserialize::<BTreeMap<String, Limit>, _>(&mut de).unwrap_err();
+            assert_eq!((err.line(), err.column()), (1, 29), "{}", err);
+        }
+
+        #[test]
+        fn missing_key() {
+            let xml = r#"<settings><add type="int">10</add></settings>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_entry_names("add", "key"));
+            let err = entries::deserialize::<BTreeMap<String, Setting>, _>(&mut de).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::Custom);
+            assert!(
+                err.to_string()
+                    .starts_with("missing attribute `key` of map entry in settings/add[0]"),
+                "{}",
+                err
+            );
+        }
+
+        #[test]
+        fn per_field() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Owner {
+                name: String,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Project {
+                #[serde(with = "entries")]
+                settings: BTreeMap<String, Setting>,
+                labels: BTreeMap<String, String>,
+                #[serde(with = "entries")]
+                optional: Option<BTreeMap<String, String>>,
+            }
+
+            let xml = r#"<project>
+                <settings>
+                    <entry type="int" key="max size">10</entry>
+                    <entry key="&lt;name&gt;" type="string">a</entry>
+                </settings>
+                <labels><entry>x</entry></labels>
+                <optional><entry key="a">b</entry></optional>
+            </project>"#;
+            let project: Project = from_str(xml).unwrap();
+            assert_eq!(project.settings, settings());
+            assert_eq!(project.labels["entry"], "x");
+            assert_eq!(project.optional.unwrap()["a"], "b");
+
+            // structures with flattened fields are maps
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Flattened {
+                #[serde(flatten)]
+                owner: Owner,
+                #[serde(with = "entries")]
+                settings: BTreeMap<String, Setting>,
+            }
+
+            let xml = r#"<Flattened><name>Ann</name><settings/></Flattened>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_entry_names("add", "key"));
+            let flattened = Flattened::deserialize(&mut de).unwrap();
+            assert_eq!(flattened.owner.name, "Ann");
+        }
+    }
+
+    mod child_elements {
+        use super::*;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Step {
+            Checkout,
+            Build {
+                #[serde(rename = "@target")]
+                target: String,
+            },
+            Run(String),
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Job {
+            #[serde(rename = "@id")]
+            id: u32,
+            name: String,
+            #[serde(rename = "$value")]
+            steps: Vec<Step>,
+            timeout: Option<u32>,
+        }
+
+        #[test]
+        fn between_fields() {
+            let xml = r#"<job id="1">
+                <name>ci</name>
+                <checkout/>
+                <build target="release"/>
+                <run>tests</run>
+                <checkout/>
+                <timeout>60</timeout>
+            </job>"#;
+            assert_eq!(
+                from_str::<Job>(xml).unwrap(),
+                Job {
+                    id: 1,
+                    name: "ci".into(),
+                    steps: vec![
+                        Step::Checkout,
+                        Step::Build {
+                            target: "release".into()
+                        },
+                        Step::Run("tests".into()),
+                        Step::Checkout,
+                    ],
+                    timeout: Some(60),
+                }
+            );
+        }
+
+        #[test]
+        fn fields_first() {
+            let xml = r#"<job id="1"><timeout>60</timeout><name>ci</name><run>a</run></job>"#;
+            let job: Job = from_str(xml).unwrap();
+            assert_eq!(job.name, "ci");
+            assert_eq!(job.steps, vec![Step::Run("a".into())]);
+            assert_eq!(job.timeout, Some(60));
+        }
+
+        #[test]
+        fn flattened_text_elements() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Owner {
+                name: String,
+                mail: Option<String>,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Project {
+                #[serde(rename = "@id")]
+                id: String,
+                #[serde(flatten)]
+                owner: Owner,
+                version: String,
+            }
+
+            let xml = r#"<project id="1"><name>Ann</name><mail>a&amp;b</mail><version>2</version></project>"#;
+            assert_eq!(
+                from_str::<Project>(xml).unwrap(),
+                Project {
+                    id: "1".into(),
+                    owner: Owner {
+                        name: "Ann".into(),
+                        mail: Some("a&b".into()),
+                    },
+                    version: "2".into(),
+                }
+            );
+        }
+
+        #[test]
+        fn flattened_struct_and_children() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Meta {
+                author: String,
+                version: String,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Job {
+                #[serde(rename = "@id")]
+                id: u32,
+                name: String,
+                #[serde(flatten)]
+                meta: Meta,
+                #[serde(flatten, with = "crate::children")]
+                steps: Vec<Step>,
+            }
+
+            let xml = r#"<job id="1"><name>ci</name><author>me</author><version>2</version><checkout/><build target="release"/><run>tests</run></job>"#;
+            assert_eq!(
+                from_str::<Job>(xml).unwrap(),
+                Job {
+                    id: 1,
+                    name: "ci".into(),
+                    meta: Meta {
+                        author: "me".into(),
+                        version: "2".into(),
+                    },
+                    steps: vec![
+                        Step::Checkout,
+                        Step::Build {
+                            target: "release".into()
+                        },
+                        Step::Run("tests".into()),
+                    ],
+                }
+            );
+        }
+
+        #[test]
+        fn children_field() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Pipeline {
+                #[serde(with = "crate::children")]
+                steps: Vec<Step>,
+            }
+
+            let xml = r#"<pipeline><steps><run>a</run><checkout/></steps></pipeline>"#;
+            assert_eq!(
+                from_str::<Pipeline>(xml).unwrap().steps,
+                vec![Step::Run("a".into()), Step::Checkout]
+            );
+        }
+    }
+
+    mod mixed_content {
+        use super::*;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Inline {
+            #[serde(rename = "$text")]
+            Text(String),
+            B(String),
+            A {
+                #[serde(rename = "@href")]
+                href: String,
+                #[serde(rename = "$value")]
+                content: Vec<Inline>,
+            },
+            Br,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Paragraph {
+            #[serde(rename = "@class")]
+            class: Option<String>,
+            #[serde(rename = "$value")]
+            content: Vec<Inline>,
+        }
+
+        #[test]
+        fn text_and_elements() {
+            let xml = r#"<p>Hello <b>world</b>!<br/><a href="x">see <b>this</b></a><![CDATA[<raw>]]></p>"#;
+            assert_eq!(
+                from_str::<Paragraph>(xml).unwrap(),
+                Paragraph {
+                    class: None,
+                    content: vec![
+                        Inline::Text("Hello ".into()),
+                        Inline::B("world".into()),
+                        Inline::Text("!".into()),
+                        Inline::Br,
+                        Inline::A {
+                            href: "x".into(),
+                            content: vec![Inline::Text("see ".into()), Inline::B("this".into())],
+                        },
+                        Inline::Text("<raw>".into()),
+                    ],
+                }
+            );
+        }
+
+        #[test]
+        fn nested_inline() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            #[serde(rename_all = "lowercase")]
+            enum Nested {
+                #[serde(rename = "$text")]
+                Text(String),
+                B(String),
+                I(Vec<Nested>),
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Paragraph {
+                #[serde(rename = "$value")]
+                content: Vec<Nested>,
+            }
+
+            let expected = Paragraph {
+                content: vec![
+                    Nested::I(vec![
+                        Nested::Text("x".into()),
+                        Nested::B("y".into()),
+                        Nested::I(vec![Nested::Text("z".into())]),
+                    ]),
+                    Nested::I(vec![]),
+                    Nested::Text("end".into()),
+                ],
+            };
+            let xml = "<p><i>x<b>y</b><i>z</i></i><i/>end</p>";
+            assert_eq!(from_str::<Paragraph>(xml).unwrap(), expected);
+            let options = DeserializerOptions::new().with_max_depth(3);
+            assert_eq!(options.from_str::<Paragraph>(xml).unwrap(), expected);
+            let options = DeserializerOptions::new().with_max_depth(2);
+            assert_eq!(
+                options.from_str::<Paragraph>(xml).unwrap_err().kind(),
+                crate::ErrorKind::Limit
+            );
+        }
+
+        #[test]
+        fn whitespace_kept() {
+            let xml = r#"<p> a <b>b</b> <a href="x"> c </a>
+</p>"#;
+            let expected = vec![
+                Inline::Text(" a ".into()),
+                Inline::B("b".into()),
+                Inline::Text(" ".into()),
+                Inline::A {
+                    href: "x".into(),
+                    content: vec![Inline::Text(" c ".into())],
+                },
+                Inline::Text("\n".into()),
+            ];
+            assert_eq!(from_str::<Paragraph>(xml).unwrap().content, expected);
+            let paragraph: Paragraph = from_reader(xml.as_bytes()).unwrap();
+            assert_eq!(paragraph.content, expected);
+        }
+
+        #[test]
+        fn round_trip() {
+            #[derive(Debug, Deserialize, serde::Serialize, PartialEq)]
+            #[serde(rename_all = "lowercase")]
+            enum Nested {
+                #[serde(rename = "$text")]
+                Text(String),
+                B(String),
+                I(Vec<Nested>),
+            }
+
+            #[derive(Debug, Deserialize, serde::Serialize, PartialEq)]
+            struct Paragraph {
+                #[serde(rename = "$value")]
+                content: Vec<Nested>,
+            }
+
+            for xml in &[
+                "<p>a <i>x<b>y</b> z</i> end</p>",
+                "<p><b>a</b> <b>b</b></p>",
+            ] {
+                let paragraph: Paragraph = from_str(xml).unwrap();
+                let mut buffer = Vec::new();
+                let mut writer = crate::Writer::new(&mut buffer);
+                let mut ser =
+                    crate::Serializer::new_with_root(&mut writer, Some("p")).with_xmldecl(false);
+                serde::Serialize::serialize(&paragraph, &mut ser).unwrap();
+                assert_eq!(String::from_utf8(buffer).unwrap(), *xml);
+            }
+        }
+
+        #[test]
+        fn text_only() {
+            let paragraph: Paragraph = from_str(r#"<p class="note">a &amp; b</p>"#).unwrap();
+            assert_eq!(paragraph.class.as_deref(), Some("note"));
+            assert_eq!(paragraph.content, vec![Inline::Text("a & b".into())]);
+        }
+
+        #[test]
+        fn field() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Item {
+                title: Inline,
+                #[serde(rename = "item")]
+                items: Vec<Inline>,
+            }
+
+            let xml =
+                r#"<root><title>text</title><item><b>bold</b></item><item>plain</item></root>"#;
+            assert_eq!(
+                from_str::<Item>(xml).unwrap(),
+                Item {
+                    title: Inline::Text("text".into()),
+                    items: vec![Inline::B("bold".into()), Inline::Text("plain".into())],
+                }
+            );
+        }
+
+        #[test]
+        fn unknown_element() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            enum Shape {
+                Circle,
+                Square,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Shapes {
+                #[serde(rename = "$value")]
+                shapes: Vec<Shape>,
+            }
+
+            let xml = "<s><Circle/><Triangle><Square/></Triangle></s>";
+            let err = from_str::<Shapes>(xml).unwrap_err();
+            assert!(
+                err.to_string().contains("unknown variant `Triangle`"),
+                "{}",
+                err
+            );
+
+            let err = from_str::<Paragraph>("<p>a <i>secret</i></p>").unwrap_err();
+            assert!(err.to_string().contains("unknown variant `i`"), "{}", err);
+        }
+    }
+
+    mod whitespace {
+        use super::*;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Snippet {
+            code: String,
+            comment: String,
+        }
+
+        #[test]
+        fn trimmed_by_default() {
+            let xml = "<snippet><code>  x  </code><comment>\n c\t</comment></snippet>";
+            let snippet: Snippet = from_str(xml).unwrap();
+            assert_eq!(snippet.code, "x");
+            assert_eq!(snippet.comment, "c");
+        }
+
+        #[test]
+        fn preserved() {
+            let xml = "<code>  x &amp; y\n</code>";
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            assert_eq!(String::deserialize(&mut de).unwrap(), "  x & y\n");
+            let mut de = Deserializer::from_reader(xml.as_bytes())
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            assert_eq!(String::deserialize(&mut de).unwrap(), "  x & y\n");
+
+            // the setting of the reader is ignored
+            let mut reader = Reader::from_reader(xml.as_bytes());
+            reader.trim_text(true);
+            let mut de = Deserializer::new(reader)
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            assert_eq!(String::deserialize(&mut de).unwrap(), "  x & y\n");
+        }
+
+        #[test]
+        fn whitespace_only() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Text {
+                #[serde(rename = "$value")]
+                text: String,
+            }
+
+            let mut de = Deserializer::from_str("<text>  </text>")
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            assert_eq!(
+                Text::deserialize(&mut de).unwrap(),
+                Text { text: "  ".into() }
+            );
+        }
+
+        #[test]
+        fn xml_space() {
+            let xml = r#"<snippet xml:space="preserve"><code> x </code><comment xml:space="default"> c </comment></snippet>"#;
+            assert_eq!(
+                from_str::<Snippet>(xml).unwrap(),
+                Snippet {
+                    code: " x ".into(),
+                    comment: "c".into(),
+                }
+            );
+            let snippet: Snippet = from_reader(xml.as_bytes()).unwrap();
+            assert_eq!(snippet.code, " x ");
+
+            let xml =
+                r#"<snippet><code xml:space="preserve"> x </code><comment> c </comment></snippet>"#;
+            let snippet: Snippet = from_str(xml).unwrap();
+            assert_eq!(snippet.code, " x ");
+            assert_eq!(snippet.comment, "c");
+
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            let snippet = Snippet::deserialize(&mut de).unwrap();
+            assert_eq!(snippet.comment, " c ");
+        }
+
+        #[test]
+        fn mixed_content() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            #[serde(rename_all = "lowercase")]
+            enum Inline {
+                #[serde(rename = "$text")]
+                Text(String),
+                B(String),
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Paragraph {
+                #[serde(rename = "$value")]
+                content: Vec<Inline>,
+            }
+
+            let xml = r#"<p xml:space="preserve">Hello <b>world</b> !</p>"#;
+            assert_eq!(
+                from_str::<Paragraph>(xml).unwrap().content,
+                vec![
+                    Inline::Text("Hello ".into()),
+                    Inline::B("world".into()),
+                    Inline::Text(" !".into()),
+                ]
+            );
+        }
+
+        #[test]
+        fn indented_output() {
+            use crate::element::Element;
+            use crate::ser::Serializer;
+            use quick_xml::Writer;
+            use serde::Serialize;
+
+            #[derive(Debug, Serialize, Deserialize, PartialEq)]
+            struct Item {
+                #[serde(rename = "@id")]
+                id: u32,
+                name: String,
+                #[serde(default)]
+                tag: Vec<String>,
+            }
+
+            #[derive(Debug, Serialize, Deserialize, PartialEq)]
+            #[serde(rename_all = "lowercase")]
+            enum Step {
+                Start,
+                Move(String),
+            }
+
+            #[derive(Debug, Serialize, Deserialize, PartialEq)]
+            struct Steps {
+                #[serde(rename = "$value")]
+                steps: Vec<Step>,
+            }
+
+            #[derive(Debug, Serialize, Deserialize, PartialEq)]
+            struct List {
+                #[serde(rename = "item")]
+                items: Vec<Item>,
+                steps: Steps,
+            }
+
+            let list = List {
+                items: vec![
+                    Item {
+                        id: 1,
+                        name: " x ".into(),
+                        tag: vec!["a".into(), "b".into()],
+                    },
+                    Item {
+                        id: 2,
+                        name: "y".into(),
+                        tag: vec![],
+                    },
+                ],
+                steps: Steps {
+                    steps: vec![Step::Start, Step::Move("left".into())],
+                },
+            };
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_indent(b' ', 2);
+            list.serialize(&mut ser).unwrap();
+            let xml = String::from_utf8(buffer).unwrap();
+            assert!(xml.contains("\n  <item"));
+
+            let options = DeserializerOptions::new().preserve_whitespace(true);
+            assert_eq!(options.from_str::<List>(&xml).unwrap(), list);
+            let mut de = Deserializer::from_reader(xml.as_bytes())
+                .with_options(DeserializerOptions::new().preserve_whitespace(true));
+            assert_eq!(List::deserialize(&mut de).unwrap(), list);
+
+            // untyped values keep all text
+            let element: Element = options.from_str(&xml).unwrap();
+            assert_eq!(element.children().len(), 3);
+            assert_eq!(element.text(), "\n  ");
+            assert_eq!(element.children()[2].tail(), "\n");
+
+            let xml = xml.replacen("<List>", r#"<List xml:space="preserve">"#, 1);
+            assert_eq!(from_str::<List>(&xml).unwrap(), list);
+        }
+    }
+
+    mod options {
+        use super::*;
+        use crate::element::Element;
+        use crate::{Booleans, ErrorKind};
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Config {
+            #[serde(rename = "@enabled")]
+            enabled: bool,
+            verbose: bool,
+            timeout: Option<u32>,
+        }
+
+        #[test]
+        fn booleans() {
+            let xml = r#"<config enabled="yes"><verbose>no</verbose></config>"#;
+            let config: Config = from_str(xml).unwrap();
+            assert!(config.enabled);
+            assert!(!config.verbose);
+
+            let strict = DeserializerOptions::new().with_booleans(Booleans::Strict);
+            let err = strict.from_str::<Config>(xml).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Invalid boolean value 'yes' in config/@enabled at 1:18"
+            );
+            let xml = r#"<config enabled="1"><verbose>no</verbose></config>"#;
+            let err = strict.from_str::<Config>(xml).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidValue);
+            assert_eq!(err.column(), 30);
+        }
+
+        #[test]
+        fn custom_booleans() {
+            let options = DeserializerOptions::new().with_booleans(Booleans::custom("Y", "N"));
+            let xml = r#"<config enabled="Y"><verbose>N</verbose></config>"#;
+            let config: Config = options.from_str(xml).unwrap();
+            assert!(config.enabled);
+            assert!(!config.verbose);
+            let element: Element = options.from_str(xml).unwrap();
+            assert_eq!(element.attribute("enabled"), Some("Y"));
+
+            let xml = r#"<config enabled="true"><verbose>N</verbose></config>"#;
+            let err = options.from_str::<Config>(xml).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidValue);
+        }
+
+        #[test]
+        fn unknown_elements() {
+            let options = DeserializerOptions::new().with_unknown_fields(UnknownFields::Error);
+            let xml = r#"<config enabled="1">
+                <verbose>1</verbose>
+                <timout>5</timout>
+            </config>"#;
+            assert_eq!(from_str::<Config>(xml).unwrap().timeout, None);
+            let err = options.from_str::<Config>(xml).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unknown);
+            assert_eq!(
+                err.to_string(),
+                "Unknown element `timout` in config/timout[0] at 3:18"
+            );
+            let err = options
+                .from_reader::<_, Config>(xml.as_bytes())
+                .unwrap_err();
+            assert_eq!(err.line(), 3);
+
+            // untyped content takes any element
+            let map: BTreeMap<String, String> =
+                options.from_str("<map><a>1</a><b>2</b></map>").unwrap();
+            assert_eq!(map.len(), 2);
+            let element: Element = options.from_str("<root><any/></root>").unwrap();
+            assert_eq!(element.children()[0].tag(), "any");
+        }
+
+        #[test]
+        fn unknown_attributes() {
+            let options = DeserializerOptions::new().with_unknown_fields(UnknownFields::Error);
+            let xml = r#"<config enabled="1" mode="fast"><verbose>1</verbose></config>"#;
+            assert!(from_str::<Config>(xml).is_ok());
+            let err = options.from_str::<Config>(xml).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unknown);
+            assert_eq!(
+                err.to_string(),
+                "Unknown attribute `mode` in config/@mode at 1:27"
+            );
+
+            let xml = r#"<config xmlns:x="urn:x" enabled="1" xml:space="default"
+                    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Config">
+                <verbose>1</verbose>
+                <timeout xsi:nil="false">5</timeout>
+            </config>"#;
+            assert!(options.from_str::<Config>(xml).is_ok());
+            let options = options.with_namespace("", "urn:none");
+            let mut de = Deserializer::from_str(xml).with_options(options);
+            assert!(Config::deserialize(&mut de).is_ok());
+        }
+
+        #[test]
+        fn unknown_content_of_values() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            enum Unit {
+                S,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Values {
+                timeout: u32,
+                name: String,
+                unit: Unit,
+                flag: (),
+            }
+
+            let options = DeserializerOptions::new().with_unknown_fields(UnknownFields::Error);
+            let xml = "<values><timeout>5</timeout><name>a</name><unit><S/></unit><flag/></values>";
+            assert!(options.from_str::<Values>(xml).is_ok());
+
+            let unknown = |xml: &str| {
+                let xml = format!("<values>{}</values>", xml);
+                assert!(from_str::<Values>(&xml).is_ok());
+                let err = options.from_str::<Values>(&xml).unwrap_err();
+                assert_eq!(err.kind(), ErrorKind::Unknown);
+                err.to_string()
+            };
+            assert_eq!(
+                unknown(r#"<timeout unit="s">5</timeout><name>a</name><unit><S/></unit><flag/>"#),
+                "Unknown attribute `unit` in values/timeout[0]/@unit at 1:24"
+            );
+            assert_eq!(
+                unknown("<timeout>5</timeout><name>a<b>x</b></name><unit><S/></unit><flag/>"),
+                "Unknown element `b` in values/name[0]/b[0] at 1:37"
+            );
+            assert_eq!(
+                unknown(r#"<timeout>5</timeout><name>a</name><unit x="1"><S/></unit><flag/>"#),
+                "Unknown attribute `x` in values/unit[0]/@x at 1:52"
+            );
+            assert_eq!(
+                unknown(r#"<timeout>5</timeout><name>a</name><unit><S y="1"/></unit><flag/>"#),
+                "Unknown attribute `y` in values/unit[0]/S[0]/@y at 1:55"
+            );
+            assert_eq!(
+                unknown("<timeout>5</timeout><name>a</name><unit><S/><T/></unit><flag/>"),
+                "Unknown element `T` in values/unit[0]/T[0] at 1:54"
+            );
+            assert_eq!(
+                unknown("<timeout>5</timeout><name>a</name><unit><S/></unit><flag><on/></flag>"),
+                "Unknown element `on` in values/flag[0]/on[0] at 1:67"
+            );
+        }
+
+        #[test]
+        fn warnings() {
+            let xml = r#"<config enabled="1" mode="fast">
+                <verbose>1</verbose>
+                <timout>5</timout>
+            </config>"#;
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_unknown_fields(UnknownFields::Warn));
+            let config = Config::deserialize(&mut de).unwrap();
+            assert_eq!(config.timeout, None);
+            let warnings: Vec<_> = de.warnings().iter().map(|w| w.to_string()).collect();
+            assert_eq!(
+                warnings,
+                [
+                    "Unknown attribute `mode` in config/@mode at 1:27",
+                    "Unknown element `timout` in config/timout[0] at 3:18",
+                ]
+            );
+            assert!(de.warnings().iter().all(|w| w.kind() == ErrorKind::Unknown));
+
+            let mut de = Deserializer::from_str(xml);
+            Config::deserialize(&mut de).unwrap();
+            assert!(de.warnings().is_empty());
+        }
+
+        #[test]
+        fn max_depth() {
+            let options = DeserializerOptions::new().with_max_depth(2);
+            let element: Element = options.from_str("<a><b/></a>").unwrap();
+            assert_eq!(element.children().len(), 1);
+            let err = options
+                .from_str::<Element>("<a><b><c/></b></a>")
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Limit);
+            assert_eq!(
+                err.to_string(),
+                "Elements nested deeper than 2 levels in a/b[0]/c[0] at 1:8"
+            );
+        }
+
+        #[test]
+        fn entities() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Product {
+                #[serde(rename = "@vendor")]
+                vendor: String,
+                name: String,
+                note: String,
+            }
+
+            let xml = r#"<product vendor="&company; Inc."><name>&product;</name><note>&lt;&product;&gt;</note></product>"#;
+            assert!(from_str::<Product>(xml).is_err());
+            let options = DeserializerOptions::new()
+                .with_entity("company", "ACME")
+                .with_entity("product", "Rocket");
+            assert_eq!(
+                options.from_str::<Product>(xml).unwrap(),
+                Product {
+                    vendor: "ACME Inc.".into(),
+                    name: "Rocket".into(),
+                    note: "<Rocket>".into(),
+                }
+            );
+        }
+
+        #[test]
+        fn end_names() {
+            let xml = "<config enabled=\"1\"><verbose>1</verbos></config>";
+            let err = from_str::<Config>(xml).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Syntax);
+            let options = DeserializerOptions::new().check_end_names(false);
+            assert!(options.from_str::<Config>(xml).unwrap().verbose);
+            assert!(
+                options
+                    .from_reader::<_, Config>(xml.as_bytes())
+                    .unwrap()
+                    .verbose
+            );
+        }
+
+        #[test]
+        fn with_options() {
+            let options = DeserializerOptions::new().preserve_whitespace(true);
+            let mut de = Deserializer::from_str("<a> x </a>").with_options(options);
+            assert_eq!(String::deserialize(&mut de).unwrap(), " x ");
+        }
+    }
+
+    mod skipped {
+        use std::collections::BTreeMap;
+
+        use super::*;
+        use crate::element::Element;
+
+        #[derive(Debug, Deserialize)]
+        struct Config {
+            #[serde(rename = "@enabled")]
+            _enabled: bool,
+            #[serde(default, rename = "timeout")]
+            _timeout: u32,
+        }
+
+        /// Path, name and content of everything skipped
+        fn skipped<T: DeserializeOwned>(xml: &str) -> Vec<(String, String, String)> {
+            let mut skipped = Vec::new();
+            let mut de = Deserializer::from_str(xml)
+                .with_options(DeserializerOptions::new().with_namespace("", "urn:x"))
+                .on_skipped(|content| {
+                    skipped.push((
+                        content.path().to_string(),
+                        content.name().to_string(),
+                        xml[content.range()].to_string(),
+                    ))
+                });
+            T::deserialize(&mut de).unwrap();
+            drop(de);
+            skipped
+        }
+
+        #[test]
+        fn elements_and_attributes() {
+            let xml = r#"<config xmlns="urn:x" enabled="1" mode='a &amp; b'>
+                <timout>5</timout>
+                <timeout>5</timeout>
+                <extra><nested/></extra>
+                <extra/>
+            </config>"#;
+            let skipped = skipped::<Config>(xml);
+            let skipped: Vec<_> = skipped
+                .iter()
+                .map(|(path, name, content)| (path.as_str(), name.as_str(), content.as_str()))
+                .collect();
+            assert_eq!(
+                skipped,
+                [
+                    ("config/@mode", "@mode", "mode='a &amp; b'"),
+                    ("config/timout[0]", "timout", "<timout>5</timout>"),
+                    ("config/extra[0]", "extra", "<extra><nested/></extra>"),
+                    ("config/extra[1]", "extra", "<extra/>"),
+                ]
+            );
+        }
+
+        #[test]
+        fn content_of_values() {
+            #[derive(Debug, Deserialize)]
+            enum Unit {
+                S,
+            }
+
+            #[derive(Debug, Deserialize)]
+            struct Values {
+                _name: String,
+                _unit: Unit,
+                _wrapped: Unit,
+                _flag: (),
+            }
+
+            let xml = r#"<Values xmlns="urn:x">
+                <_name lang="en">a<b>x</b></_name>
+                <_unit><S id="1"><sub/></S></_unit>
+                <_wrapped><S/><T/></_wrapped>
+                <_flag><on/></_flag>
+            </Values>"#;
+            let skipped = skipped::<Values>(xml);
+            let skipped: Vec<_> = skipped
+                .iter()
+                .map(|(path, name, content)| (path.as_str(), name.as_str(), content.as_str()))
+                .collect();
+            assert_eq!(
+                skipped,
+                [
+                    ("Values/_name[0]/@lang", "@lang", r#"lang="en""#),
+                    ("Values/_name[0]/b[0]", "b", "<b>x</b>"),
+                    ("Values/_unit[0]/S[0]/@id", "@id", r#"id="1""#),
+                    ("Values/_unit[0]/S[0]/sub[0]", "sub", "<sub/>"),
+                    ("Values/_wrapped[0]/T[0]", "T", "<T/>"),
+                    ("Values/_flag[0]/on[0]", "on", "<on/>"),
+                ]
+            );
+        }
+
+        #[test]
+        fn untyped() {
+            let xml = r#"<root a="1"><b>text</b></root>"#;
+            assert!(skipped::<Element>(xml).is_empty());
+            assert!(skipped::<BTreeMap<String, String>>(xml).is_empty());
+        }
+
+        #[test]
+        fn reader() {
+            let xml = "<config enabled=\"1\">\n<timout>5</timout>\n</config>";
+            let mut ranges = Vec::new();
+            let mut de = Deserializer::from_reader(xml.as_bytes())
+                .on_skipped(|content| ranges.push(content.range()));
+            Config::deserialize(&mut de).unwrap();
+            drop(de);
+            assert_eq!(ranges.len(), 1);
+            assert_eq!(&xml[ranges[0].clone()], "<timout>5</timout>");
+        }
+    }
 }
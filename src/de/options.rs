@@ -0,0 +1,316 @@
+//! Configuration of the `Deserializer`
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+use quick_xml::escape::{unescape, unescape_with};
+use serde::de::{Deserialize, DeserializeOwned};
+
+use crate::de::{Deserializer, XmlRead};
+use crate::{BinaryEncoding, Booleans, Encoding, Error, XML_NAMESPACE};
+
+/// Handling of elements and attributes, which are no field of the structure they are in
+///
+/// Attributes and child elements of primitive values are unknown as well. Namespace
+/// declarations and `xml:*` and `xsi:*` attributes are never unknown.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UnknownFields {
+    /// Skip them, unless the type denies unknown fields
+    Ignore,
+    /// Skip them and collect a warning, see [`Deserializer::warnings`]
+    Warn,
+    /// Fail with [`ErrorKind::Unknown`](crate::ErrorKind::Unknown)
+    Error,
+}
+
+impl Default for UnknownFields {
+    fn default() -> Self {
+        UnknownFields::Ignore
+    }
+}
+
+/// Options for a [`Deserializer`]
+///
+/// The same options can be used for many documents, either with the `from_*` methods or with
+/// [`Deserializer::with_options`].
+///
+/// # Examples
+///
+/// ```
+/// use serde::Deserialize;
+/// use xserde::{Booleans, DeserializerOptions, ErrorKind, UnknownFields};
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Config {
+///     verbose: bool,
+///     timeout: Option<u32>,
+/// }
+///
+/// let options = DeserializerOptions::new()
+///     .with_booleans(Booleans::Strict)
+///     .with_unknown_fields(UnknownFields::Error)
+///     .with_max_depth(16);
+///
+/// let config: Config = options.from_str("<config><verbose>1</verbose></config>").unwrap();
+/// assert!(config.verbose);
+///
+/// let err = options
+///     .from_str::<Config>("<config><verbose>1</verbose><timout>5</timout></config>")
+///     .unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::Unknown);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeserializerOptions {
+    pub(crate) booleans: Booleans,
+    pub(crate) preserve_whitespace: bool,
+    pub(crate) check_end_names: bool,
+    pub(crate) unknown_fields: UnknownFields,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) entities: HashMap<Vec<u8>, Vec<u8>>,
+    pub(crate) binary_encoding: BinaryEncoding,
+    pub(crate) resolve_namespaces: bool,
+    /// Fixed prefixes for namespace URIs
+    pub(crate) prefixes: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Element and attribute name of maps written as `<entry key="k">value</entry>`
+    pub(crate) entry_names: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        let mut prefixes = BTreeMap::new();
+        prefixes.insert(XML_NAMESPACE.as_bytes().to_vec(), b"xml".to_vec());
+        DeserializerOptions {
+            booleans: Booleans::default(),
+            preserve_whitespace: false,
+            check_end_names: true,
+            unknown_fields: UnknownFields::default(),
+            max_depth: None,
+            entities: HashMap::new(),
+            binary_encoding: BinaryEncoding::default(),
+            resolve_namespaces: false,
+            prefixes,
+            entry_names: None,
+        }
+    }
+}
+
+impl DeserializerOptions {
+    /// Default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Literals accepted for booleans, [`Booleans::Lenient`] by default
+    pub fn with_booleans(mut self, booleans: Booleans) -> Self {
+        self.booleans = booleans;
+        self
+    }
+
+    /// Keep texts exactly as written instead of trimming them and skipping texts of only
+    /// whitespace
+    ///
+    /// Elements with an `xml:space` attribute override this setting for their content:
+    /// `preserve` keeps whitespace and `default` trims texts.
+    /// Text read into a `$text` variant of mixed content is always kept as written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::DeserializerOptions;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Snippet {
+    ///     code: String,
+    ///     comment: String,
+    /// }
+    ///
+    /// let xml = r#"<snippet>
+    ///     <code xml:space="preserve">  x = 1 </code>
+    ///     <comment> trimmed </comment>
+    /// </snippet>"#;
+    ///
+    /// let snippet: Snippet = xserde::from_str(xml).unwrap();
+    /// assert_eq!(snippet.code, "  x = 1 ");
+    /// assert_eq!(snippet.comment, "trimmed");
+    ///
+    /// let options = DeserializerOptions::new().preserve_whitespace(true);
+    /// assert_eq!(options.from_str::<String>("<comment> kept </comment>").unwrap(), " kept ");
+    /// ```
+    pub fn preserve_whitespace(mut self, preserve: bool) -> Self {
+        self.preserve_whitespace = preserve;
+        self
+    }
+
+    /// Fail when the name of an end tag differs from its start tag, enabled by default
+    pub fn check_end_names(mut self, check: bool) -> Self {
+        self.check_end_names = check;
+        self
+    }
+
+    /// Handling of elements and attributes, which are no field of the structure they are in
+    ///
+    /// Structures with a `$value` field take all elements. Flattened structures and maps are
+    /// not checked. Namespace declarations and attributes like `xml:space` are always allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::{Deserializer, DeserializerOptions, UnknownFields};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     timeout: Option<u32>,
+    /// }
+    ///
+    /// let xml = "<config>\n  <timout>5</timout>\n</config>";
+    /// let options = DeserializerOptions::new().with_unknown_fields(UnknownFields::Warn);
+    /// let mut de = Deserializer::from_str(xml).with_options(options);
+    /// let config = Config::deserialize(&mut de).unwrap();
+    /// assert_eq!(config.timeout, None);
+    /// assert_eq!(
+    ///     de.warnings()[0].to_string(),
+    ///     "Unknown element `timout` in config/timout[0] at 2:4"
+    /// );
+    /// ```
+    pub fn with_unknown_fields(mut self, unknown: UnknownFields) -> Self {
+        self.unknown_fields = unknown;
+        self
+    }
+
+    /// Fail with [`ErrorKind::Limit`](crate::ErrorKind::Limit) for elements nested deeper than
+    /// `depth`, the root element has the depth 1
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Replace the entity reference `&name;` in text and attribute values by `value`
+    ///
+    /// Only the predefined entities and character references are known by default. Entities
+    /// declared in the document type declaration are not read.
+    pub fn with_entity(mut self, name: &str, value: &str) -> Self {
+        self.entities
+            .insert(name.as_bytes().to_vec(), value.as_bytes().to_vec());
+        self
+    }
+
+    /// Encoding for bytes, base64 by default
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+        self
+    }
+
+    /// Resolve namespaces of element and attribute names
+    ///
+    /// Names in a namespace are matched as `{uri}local`, names in a namespace registered with
+    /// [`with_namespace`](#method.with_namespace) as `prefix:local`. The prefix used in the
+    /// document does not matter. Namespace declarations (`xmlns` attributes) are not provided
+    /// as attributes.
+    ///
+    /// Without namespace resolution elements are matched by their local name and attributes by
+    /// their name as written in the document.
+    pub fn resolve_namespaces(mut self, resolve: bool) -> Self {
+        self.resolve_namespaces = resolve;
+        self
+    }
+
+    /// Match names in the namespace `uri` as `prefix:local` or as `local` for an empty prefix
+    ///
+    /// Enables namespace resolution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use xserde::DeserializerOptions;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Envelope {
+    ///     #[serde(rename = "soap:Body")]
+    ///     body: String,
+    /// }
+    ///
+    /// let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope">
+    ///     <s:Body>content</s:Body>
+    /// </s:Envelope>"#;
+    ///
+    /// let options = DeserializerOptions::new()
+    ///     .with_namespace("soap", "http://www.w3.org/2003/05/soap-envelope");
+    /// let envelope: Envelope = options.from_reader(xml.as_bytes()).unwrap();
+    /// assert_eq!(envelope.body, "content");
+    /// ```
+    pub fn with_namespace(mut self, prefix: &str, uri: &str) -> Self {
+        self.prefixes
+            .insert(uri.as_bytes().to_vec(), prefix.as_bytes().to_vec());
+        self.resolve_namespaces = true;
+        self
+    }
+
+    /// Read map entries from elements `element`, which have the key in the attribute `key`,
+    /// instead of `<entry key="k">value</entry>`
+    ///
+    /// These names are used for fields with [`entries`](crate::entries).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use serde::Deserialize;
+    /// use xserde::DeserializerOptions;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Prices {
+    ///     #[serde(with = "xserde::entries")]
+    ///     items: BTreeMap<String, u32>,
+    /// }
+    ///
+    /// let xml = r#"<prices><items><item name="apple">2</item><item name="pear">3</item></items></prices>"#;
+    /// let options = DeserializerOptions::new().with_entry_names("item", "name");
+    /// let prices: Prices = options.from_str(xml).unwrap();
+    /// assert_eq!(prices.items["pear"], 3);
+    /// ```
+    pub fn with_entry_names(mut self, element: &str, key: &str) -> Self {
+        self.entry_names = Some((element.as_bytes().to_vec(), key.as_bytes().to_vec()));
+        self
+    }
+
+    /// Deserialize a xml string, see [`from_str`](crate::from_str)
+    pub fn from_str<'de, T: Deserialize<'de>>(&self, s: &'de str) -> Result<T, Error> {
+        self.deserialize(Deserializer::from_str(s))
+    }
+
+    /// Deserialize from bytes, see [`from_slice`](crate::from_slice)
+    pub fn from_slice<'de, T: Deserialize<'de>>(&self, v: &'de [u8]) -> Result<T, Error> {
+        if let Ok((Encoding::Utf8, _)) = Encoding::detect(v) {
+            self.deserialize(Deserializer::from_slice(v))
+        } else {
+            self.deserialize(Deserializer::from_reader(v))
+        }
+    }
+
+    /// Deserialize from a reader, see [`from_reader`](crate::from_reader)
+    pub fn from_reader<R: BufRead, T: DeserializeOwned>(&self, reader: R) -> Result<T, Error> {
+        self.deserialize(Deserializer::from_reader(reader))
+    }
+
+    fn deserialize<'de, R: XmlRead<'de>, T: Deserialize<'de>>(
+        &self,
+        de: Deserializer<'de, R>,
+    ) -> Result<T, Error> {
+        let mut de = de.with_options(self.clone());
+        T::deserialize(&mut de).map_err(|err| de.fix_position(err))
+    }
+
+    /// Replaces entity and character references in `text`
+    pub(crate) fn unescape<'a>(&self, text: &'a [u8]) -> quick_xml::Result<Cow<'a, [u8]>> {
+        let result = if self.entities.is_empty() {
+            unescape(text)
+        } else {
+            unescape_with(text, &self.entities)
+        };
+        result.map_err(quick_xml::Error::EscapeError)
+    }
+}
@@ -0,0 +1,187 @@
+//! Location of the current event in the element tree
+
+use std::fmt;
+use std::ops::Range;
+
+/// An open element
+#[derive(Default)]
+struct Element {
+    /// End of the name in `ElementPath::names`, the name starts at the end of the parent's name
+    name_end: usize,
+    /// Index among the previous siblings with the same name
+    index: usize,
+    /// Start of the counters of the children in `ElementPath::children`
+    children_start: usize,
+    /// `xml:space` is `preserve` or `default`
+    preserve_space: Option<bool>,
+}
+
+/// Path of open elements like `gpx/trk[2]/trkseg[0]`
+///
+/// Names are kept in shared buffers, which are reused for all elements, so an element does not
+/// allocate once the buffers are large enough.
+pub(crate) struct ElementPath {
+    /// Open elements, the first entry stands for the document
+    elements: Vec<Element>,
+    /// Concatenated names of the open elements
+    names: Vec<u8>,
+    /// Name in `child_names` and number of the children per name, grouped by open element
+    children: Vec<(Range<usize>, usize)>,
+    /// Names of the children of open elements
+    child_names: Vec<u8>,
+    /// The last element is closed, but is kept until the next event
+    closed: bool,
+}
+
+impl ElementPath {
+    pub fn new() -> Self {
+        ElementPath {
+            elements: vec![Element::default()],
+            names: Vec::new(),
+            children: Vec::new(),
+            child_names: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// An element was started
+    pub fn start(&mut self, name: &[u8]) {
+        self.next();
+        let parent = self.elements.last().unwrap();
+        let child_names = &self.child_names;
+        let counter = self.children[parent.children_start..]
+            .iter_mut()
+            .find(|(child, _)| child_names[child.clone()] == *name);
+        let index = match counter {
+            Some((_, count)) => {
+                *count += 1;
+                *count - 1
+            }
+            None => {
+                let start = self.child_names.len();
+                self.child_names.extend_from_slice(name);
+                self.children.push((start..self.child_names.len(), 1));
+                0
+            }
+        };
+        self.names.extend_from_slice(name);
+        self.elements.push(Element {
+            name_end: self.names.len(),
+            index,
+            children_start: self.children.len(),
+            preserve_space: None,
+        });
+    }
+
+    /// The last element was ended
+    pub fn end(&mut self) {
+        self.next();
+        self.closed = self.elements.len() > 1;
+    }
+
+    /// Any other event was read
+    pub fn next(&mut self) {
+        if self.closed {
+            let element = self.elements.pop().unwrap();
+            if let Some((name, _)) = self.children.get(element.children_start) {
+                self.child_names.truncate(name.start);
+            }
+            self.children.truncate(element.children_start);
+            let parent = self.elements.last().unwrap();
+            self.names.truncate(parent.name_end);
+            self.closed = false;
+        }
+    }
+
+    /// Set `xml:space` of the last started element
+    pub fn set_preserve_space(&mut self, preserve: bool) {
+        self.elements.last_mut().unwrap().preserve_space = Some(preserve);
+    }
+
+    /// `xml:space` in scope of the next event
+    pub fn preserve_space(&self) -> Option<bool> {
+        let open = self.elements.len() - self.closed as usize;
+        self.elements[..open]
+            .iter()
+            .rev()
+            .find_map(|element| element.preserve_space)
+    }
+
+    /// Number of open elements
+    pub fn depth(&self) -> usize {
+        self.elements.len() - 1 - self.closed as usize
+    }
+
+    /// Path of the attribute `name` of the last element
+    pub fn attribute(&self, name: &[u8]) -> String {
+        let path = self.to_string();
+        let name = String::from_utf8_lossy(name);
+        if path.is_empty() {
+            name.into_owned()
+        } else {
+            format!("{}/{}", path, name)
+        }
+    }
+}
+
+impl fmt::Display for ElementPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pair) in self.elements.windows(2).enumerate() {
+            let (parent, element) = (&pair[0], &pair[1]);
+            let name = String::from_utf8_lossy(&self.names[parent.name_end..element.name_end]);
+            // the root element has no siblings
+            if i == 0 {
+                write!(f, "{}", name)?;
+            } else {
+                write!(f, "/{}[{}]", name, element.index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn siblings() {
+        let mut path = ElementPath::new();
+        path.start(b"gpx");
+        path.start(b"trk");
+        path.end();
+        assert_eq!(path.to_string(), "gpx/trk[0]");
+        path.start(b"name");
+        path.end();
+        path.start(b"trk");
+        assert_eq!(path.to_string(), "gpx/trk[1]");
+        assert_eq!(path.attribute(b"@id"), "gpx/trk[1]/@id");
+        path.end();
+        path.next();
+        assert_eq!(path.to_string(), "gpx");
+        path.end();
+        path.end();
+        assert_eq!(path.to_string(), "");
+    }
+
+    #[test]
+    fn counters_per_parent() {
+        let mut path = ElementPath::new();
+        path.start(b"gpx");
+        for _ in 0..2 {
+            path.start(b"trk");
+            path.start(b"trkseg");
+            path.end();
+            path.start(b"trkseg");
+            assert_eq!(path.depth(), 3);
+            path.end();
+            path.end();
+        }
+        assert_eq!(path.to_string(), "gpx/trk[1]");
+        path.start(b"trkseg");
+        assert_eq!(path.to_string(), "gpx/trkseg[0]");
+        path.end();
+        path.start(b"wpt");
+        assert_eq!(path.to_string(), "gpx/wpt[0]");
+    }
+}
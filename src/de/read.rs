@@ -0,0 +1,515 @@
+//! Sources of XML events for the `Deserializer`
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Read};
+
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Reader;
+
+use crate::{Encoding, Error};
+
+/// Source of XML events for the [`Deserializer`](super::Deserializer)
+///
+/// Events of the lifetime `'i` can be borrowed from the input, which allows
+/// deserialization of borrowed `&'i str` fields.
+pub trait XmlRead<'i> {
+    /// Input of the underlying reader
+    type Input: BufRead;
+
+    /// Read the next event
+    ///
+    /// Namespace declarations are tracked in `namespace_buffer` when given.
+    fn next(&mut self, namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'i>, Error>;
+
+    /// Trim whitespace around texts and skip texts of only whitespace with [`next`](#tymethod.next)
+    ///
+    /// Enabled by default.
+    fn trim_text(&mut self, trim: bool);
+
+    /// Fail when the name of an end tag differs from its start tag
+    ///
+    /// Enabled by default.
+    fn check_end_names(&mut self, check: bool);
+
+    /// Read the next event, which may borrow from an internal buffer
+    ///
+    /// Used to skip events without copying them.
+    fn read_event(&mut self, namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'_>, Error>;
+
+    /// `slice` with the lifetime of the input, if it is part of the input
+    fn borrow(&self, slice: &[u8]) -> Option<&'i [u8]>;
+
+    /// Offset of the content of the last event returned by [`next`](#tymethod.next)
+    fn event_offset(&self) -> usize;
+
+    /// Line and column of `offset`, both starting at 1
+    ///
+    /// Columns are counted in bytes. Offsets before the last event may be located imprecisely.
+    fn location(&self, offset: usize) -> (usize, usize);
+
+    /// The underlying reader
+    fn reader(&self) -> &Reader<Self::Input>;
+}
+
+/// Source of XML events from a [`BufRead`]
+///
+/// All events are copied out of the internal buffer.
+pub struct IoReader<R: BufRead> {
+    reader: Reader<R>,
+    /// Raw content of the last event
+    buf: Vec<u8>,
+    /// Position of `buf` in the input
+    position: usize,
+    /// Offset of the content of the last event, which can differ from `position` for texts
+    offset: usize,
+    /// The last event was a text, so the `<` of the next markup is already consumed
+    after_text: bool,
+    /// Number of line breaks before `position`
+    lines: usize,
+    /// Start of the line containing `position`
+    line_start: usize,
+    /// Trim texts
+    trim: bool,
+}
+
+impl<R: BufRead> IoReader<R> {
+    /// Read events from a configured `Reader`
+    ///
+    /// Text is trimmed here instead of by the `Reader`, so that line breaks can be counted. The
+    /// `trim_text` setting of `reader` is ignored, use [`trim_text`](XmlRead::trim_text) instead.
+    pub fn new(mut reader: Reader<R>) -> Self {
+        reader.trim_text(false);
+        IoReader {
+            reader,
+            buf: Vec::new(),
+            position: 0,
+            offset: 0,
+            after_text: false,
+            lines: 0,
+            line_start: 0,
+            trim: true,
+        }
+    }
+
+    /// The underlying reader
+    pub fn into_inner(self) -> Reader<R> {
+        self.reader
+    }
+}
+
+impl<'i, R: BufRead> XmlRead<'i> for IoReader<R> {
+    type Input = R;
+
+    fn next(&mut self, mut namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'i>, Error> {
+        loop {
+            let event = self
+                .read_event(namespace_buffer.as_deref_mut())?
+                .into_owned();
+            return Ok(match event {
+                Event::Text(e) if !self.trim && !e.is_empty() => Event::Text(e),
+                Event::Text(e) => match trim(&e) {
+                    Some((start, end)) => {
+                        self.offset += start;
+                        Event::Text(BytesText::from_escaped(e[start..end].to_vec()))
+                    }
+                    None => continue,
+                },
+                event => event,
+            });
+        }
+    }
+
+    fn trim_text(&mut self, trim: bool) {
+        self.trim = trim;
+    }
+
+    fn check_end_names(&mut self, check: bool) {
+        self.reader.check_end_names(check);
+    }
+
+    fn read_event(&mut self, namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'_>, Error> {
+        // line breaks of the last event are counted, when it is not needed anymore
+        for (i, _) in self.buf.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+            self.lines += 1;
+            self.line_start = self.position + i + 1;
+        }
+        self.position = self.reader.buffer_position() + self.after_text as usize;
+        self.offset = self.position;
+        self.buf.clear();
+        let event = match namespace_buffer {
+            Some(namespace_buffer) => {
+                self.reader
+                    .read_namespaced_event(&mut self.buf, namespace_buffer)?
+                    .1
+            }
+            None => self.reader.read_event(&mut self.buf)?,
+        };
+        self.after_text = matches!(event, Event::Text(_));
+        Ok(event)
+    }
+
+    fn borrow(&self, _slice: &[u8]) -> Option<&'i [u8]> {
+        None
+    }
+
+    fn event_offset(&self) -> usize {
+        self.offset
+    }
+
+    fn location(&self, offset: usize) -> (usize, usize) {
+        let mut lines = self.lines;
+        let mut line_start = self.line_start.min(offset);
+        let end = offset.saturating_sub(self.position).min(self.buf.len());
+        for (i, _) in self.buf[..end]
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'\n')
+        {
+            lines += 1;
+            line_start = self.position + i + 1;
+        }
+        (lines + 1, offset - line_start + 1)
+    }
+
+    fn reader(&self) -> &Reader<R> {
+        &self.reader
+    }
+}
+
+/// Maximum length of the start of a document, which is searched for the XML declaration
+const MAX_HEAD: usize = 1024;
+
+/// Adapter, which transcodes a document to UTF-8
+///
+/// The encoding is detected from the byte order mark or the XML declaration, unless it is
+/// given explicitly. UTF-8 input is passed through.
+pub struct DecodingReader<R> {
+    inner: R,
+    /// Given or detected encoding, `None` before the start of the document is read
+    encoding: Option<Encoding>,
+    /// The start of the document is read
+    started: bool,
+    /// Decoded input, which is not consumed yet
+    decoded: Vec<u8>,
+    /// Position in `decoded`
+    pos: usize,
+    /// Input, which is not decoded yet
+    undecoded: Vec<u8>,
+}
+
+impl<R: BufRead> DecodingReader<R> {
+    /// Read `inner` in the encoding of the document
+    pub fn new(inner: R) -> Self {
+        DecodingReader {
+            inner,
+            encoding: None,
+            started: false,
+            decoded: Vec::new(),
+            pos: 0,
+            undecoded: Vec::new(),
+        }
+    }
+
+    /// Read `inner` in `encoding`, regardless of the encoding declared by the document
+    pub fn with_encoding(inner: R, encoding: Encoding) -> Self {
+        DecodingReader {
+            encoding: Some(encoding),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Given or detected encoding, `None` before anything is read
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding.filter(|_| self.started)
+    }
+
+    /// The underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the start of the document and detect the encoding
+    fn start(&mut self) -> io::Result<()> {
+        while self.undecoded.len() < MAX_HEAD && !self.undecoded.windows(2).any(|w| w == b"?>") {
+            let chunk = self.inner.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let len = chunk.len().min(MAX_HEAD);
+            self.undecoded.extend_from_slice(&chunk[..len]);
+            self.inner.consume(len);
+        }
+
+        let (encoding, bom) = match self.encoding {
+            Some(encoding) => (encoding, encoding.bom_len(&self.undecoded)),
+            None => Encoding::detect(&self.undecoded).map_err(invalid_data)?,
+        };
+        self.undecoded.drain(..bom);
+        self.encoding = Some(encoding);
+        self.started = true;
+        if encoding == Encoding::Utf8 {
+            // passed through after the start
+            std::mem::swap(&mut self.decoded, &mut self.undecoded);
+        }
+        Ok(())
+    }
+
+    /// Decode the next chunk of the input into `decoded`
+    fn decode(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.decoded.clear();
+        self.pos = 0;
+        while self.decoded.is_empty() {
+            let chunk = self.inner.fill_buf()?;
+            let eof = chunk.is_empty();
+            let len = chunk.len();
+            self.undecoded.extend_from_slice(chunk);
+            self.inner.consume(len);
+
+            let mut decoded = String::new();
+            let len = encoding
+                .decode(&self.undecoded, &mut decoded)
+                .map_err(invalid_data)?;
+            self.undecoded.drain(..len);
+            self.decoded = decoded.into_bytes();
+            if eof {
+                if !self.undecoded.is_empty() {
+                    return Err(invalid_data("incomplete character at the end"));
+                }
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<R: BufRead> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.started {
+            self.start()?;
+        }
+        if self.pos == self.decoded.len() {
+            match self.encoding {
+                Some(Encoding::Utf8) => return self.inner.fill_buf(),
+                Some(encoding) => self.decode(encoding)?,
+                None => unreachable!(),
+            }
+        }
+        Ok(&self.decoded[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos < self.decoded.len() {
+            self.pos += amt;
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}
+
+/// Source of XML events from a byte slice
+///
+/// Event contents are borrowed from the input by their position. This is not zero-copy: the reader
+/// of quick-xml 0.22 has no borrowing API, so it still copies each event to an internal buffer,
+/// but the copies are only compared with the input and not handed out.
+pub struct SliceReader<'i> {
+    reader: Reader<&'i [u8]>,
+    input: &'i [u8],
+    buf: Vec<u8>,
+    /// Offset of the content of the last event
+    offset: usize,
+    /// The last event was a text, so the `<` of the next markup is already consumed
+    after_text: bool,
+    /// Trim texts
+    trim: bool,
+    /// Offset of the name of the last start element, which is also the name of the end of an
+    /// empty element
+    start_name: usize,
+}
+
+impl<'i> SliceReader<'i> {
+    /// Read events from `input`
+    pub fn new(input: &'i [u8]) -> Self {
+        let mut reader = Reader::from_reader(input);
+        // text is trimmed here, so that the internal buffer always mirrors the input
+        reader
+            .expand_empty_elements(true)
+            .check_end_names(true)
+            .trim_text(false);
+        SliceReader {
+            reader,
+            input,
+            buf: Vec::new(),
+            offset: 0,
+            after_text: false,
+            trim: true,
+            start_name: 0,
+        }
+    }
+}
+
+/// Part of the input at `position`, from where the reader copied `content`
+///
+/// Falls back to a copy of `content` if the input differs, so a wrong position can never hand out
+/// other data than the reader read.
+fn borrow_input<'i>(input: &'i [u8], position: usize, content: &[u8]) -> Cow<'i, [u8]> {
+    match input.get(position..position + content.len()) {
+        Some(slice) if slice == content => Cow::Borrowed(slice),
+        _ => Cow::Owned(content.to_vec()),
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\r' | b'\n' | b'\t')
+}
+
+/// Range of `text` without surrounding whitespace, `None` if there is only whitespace
+pub(crate) fn trim(text: &[u8]) -> Option<(usize, usize)> {
+    let start = text.iter().position(|&b| !is_whitespace(b))?;
+    let end = text.iter().rposition(|&b| !is_whitespace(b)).unwrap() + 1;
+    Some((start, end))
+}
+
+fn borrow_start<'i>(input: &'i [u8], position: usize, start: &BytesStart) -> BytesStart<'i> {
+    let name_len = start.name().len();
+    match borrow_input(input, position, start) {
+        Cow::Borrowed(content) => BytesStart::borrowed(content, name_len),
+        Cow::Owned(content) => BytesStart::owned(content, name_len),
+    }
+}
+
+fn borrow_end<'i>(input: &'i [u8], position: usize, name: &[u8]) -> BytesEnd<'i> {
+    match borrow_input(input, position, name) {
+        Cow::Borrowed(name) => BytesEnd::borrowed(name),
+        Cow::Owned(name) => BytesEnd::owned(name),
+    }
+}
+
+impl<'i> XmlRead<'i> for SliceReader<'i> {
+    type Input = &'i [u8];
+
+    fn next(&mut self, mut namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'i>, Error> {
+        loop {
+            let input = self.input;
+            // position of the content of the next event
+            let position = self.reader.buffer_position() + self.after_text as usize;
+            self.buf.clear();
+            let event = match namespace_buffer.as_deref_mut() {
+                Some(namespace_buffer) => {
+                    self.reader
+                        .read_namespaced_event(&mut self.buf, namespace_buffer)?
+                        .1
+                }
+                None => self.reader.read_event(&mut self.buf)?,
+            };
+            self.after_text = matches!(event, Event::Text(_));
+            self.offset = position;
+
+            // offsets of the content in the raw markup, see `quick_xml::Reader`
+            return Ok(match event {
+                Event::Text(e) => {
+                    let text = e.escaped();
+                    let (start, end) = match trim(text) {
+                        _ if !self.trim && !text.is_empty() => (0, text.len()),
+                        Some(range) => range,
+                        None => continue,
+                    };
+                    self.offset += start;
+                    Event::Text(BytesText::from_escaped(borrow_input(
+                        input,
+                        position + start,
+                        &text[start..end],
+                    )))
+                }
+                Event::Start(e) => {
+                    self.start_name = position;
+                    Event::Start(borrow_start(input, position, &e))
+                }
+                Event::Empty(e) => Event::Empty(borrow_start(input, position, &e)),
+                // the end of an empty element is not in the input
+                Event::End(e) if self.reader.buffer_position() == position => {
+                    Event::End(borrow_end(input, self.start_name, e.name()))
+                }
+                Event::End(e) => Event::End(borrow_end(input, position + 1, e.name())),
+                // quick-xml escapes the content of CDATA sections, which only makes it longer
+                Event::CData(e) => {
+                    let raw = &input[position + 8..self.reader.buffer_position() - 3];
+                    if raw.len() == e.escaped().len() {
+                        Event::CData(BytesText::from_escaped(raw))
+                    } else {
+                        Event::CData(e.into_owned())
+                    }
+                }
+                Event::Comment(e) => Event::Comment(BytesText::from_escaped(borrow_input(
+                    input,
+                    position + 3,
+                    e.escaped(),
+                ))),
+                Event::PI(e) => Event::PI(BytesText::from_escaped(borrow_input(
+                    input,
+                    position + 1,
+                    e.escaped(),
+                ))),
+                Event::DocType(e) => Event::DocType(BytesText::from_escaped(borrow_input(
+                    input,
+                    position + 8,
+                    e.escaped(),
+                ))),
+                Event::Decl(e) => Event::Decl(BytesDecl::from_start(borrow_start(
+                    input,
+                    position + 1,
+                    &BytesStart::borrowed(&e[..], 3),
+                ))),
+                Event::Eof => Event::Eof,
+            });
+        }
+    }
+
+    fn trim_text(&mut self, trim: bool) {
+        self.trim = trim;
+    }
+
+    fn check_end_names(&mut self, check: bool) {
+        self.reader.check_end_names(check);
+    }
+
+    fn read_event(&mut self, namespace_buffer: Option<&mut Vec<u8>>) -> Result<Event<'_>, Error> {
+        self.next(namespace_buffer)
+    }
+
+    fn borrow(&self, slice: &[u8]) -> Option<&'i [u8]> {
+        let offset = (slice.as_ptr() as usize).checked_sub(self.input.as_ptr() as usize)?;
+        self.input.get(offset..offset + slice.len())
+    }
+
+    fn event_offset(&self) -> usize {
+        self.offset
+    }
+
+    fn location(&self, offset: usize) -> (usize, usize) {
+        let before = &self.input[..offset.min(self.input.len())];
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let lines = before.iter().filter(|&&b| b == b'\n').count();
+        (lines + 1, offset - line_start + 1)
+    }
+
+    fn reader(&self) -> &Reader<&'i [u8]> {
+        &self.reader
+    }
+}
@@ -1,40 +1,53 @@
-use std::io::BufRead;
-
 use quick_xml::events::{BytesStart, Event};
 use serde::de;
 
-use crate::de::Deserializer;
+use crate::de::{Deserializer, XmlRead};
 use crate::Error;
 
 #[derive(Debug)]
 enum Names {
     Unknown,
     Peek(Vec<u8>),
+    /// Any element, which is not one of the fields of the parent structure
+    Except(&'static [&'static str]),
 }
 
 impl Names {
-    fn is_valid(&self, start: &BytesStart) -> bool {
+    fn is_valid<'de, R: XmlRead<'de>>(
+        &self,
+        de: &Deserializer<'de, R>,
+        start: &BytesStart,
+    ) -> bool {
         match self {
             Names::Unknown => true,
-            Names::Peek(n) => **n == *start.name(),
+            Names::Peek(n) => **n == *de.element_name(start),
+            Names::Except(fields) => {
+                let key = de.element_key(start);
+                !fields.iter().any(|field| field.as_bytes() == &*key)
+            }
         }
     }
 }
 
 /// A SeqAccess
-pub struct SeqAccess<'a, R: BufRead> {
-    de: &'a mut Deserializer<R>,
+pub struct SeqAccess<'de, 'a, R: XmlRead<'de>> {
+    de: &'a mut Deserializer<'de, R>,
     max_size: Option<usize>,
     names: Names,
+    /// An item was read from text, so whitespace-only text is also an item
+    text_items: bool,
 }
 
-impl<'a, R: BufRead> SeqAccess<'a, R> {
+impl<'de, 'a, R: XmlRead<'de>> SeqAccess<'de, 'a, R> {
     /// Get a new SeqAccess
-    pub fn new(de: &'a mut Deserializer<R>, max_size: Option<usize>) -> Result<Self, Error> {
-        let names = if de.has_value_field {
-            Names::Unknown
-        } else if let Some(Event::Start(e)) = de.peek()? {
-            Names::Peek(e.name().to_vec())
+    pub fn new(de: &'a mut Deserializer<'de, R>, max_size: Option<usize>) -> Result<Self, Error> {
+        let names = if let Some(fields) = de.value_fields.take() {
+            Names::Except(fields)
+        } else if let Some(Event::Start(_)) = de.peek()? {
+            match &de.peek {
+                Some(Event::Start(e)) => Names::Peek(de.element_name(e).into_owned()),
+                _ => unreachable!(),
+            }
         } else {
             Names::Unknown
         };
@@ -42,11 +55,23 @@ impl<'a, R: BufRead> SeqAccess<'a, R> {
             de,
             max_size,
             names,
+            text_items: false,
         })
     }
+
+    /// Get a SeqAccess for all child elements and text of the current element
+    pub fn content(de: &'a mut Deserializer<'de, R>) -> Self {
+        de.value_fields = None;
+        SeqAccess {
+            de,
+            max_size: None,
+            names: Names::Unknown,
+            text_items: false,
+        }
+    }
 }
 
-impl<'de, 'a, R: 'a + BufRead> de::SeqAccess<'de> for SeqAccess<'a, R> {
+impl<'de, 'a, R: 'a + XmlRead<'de>> de::SeqAccess<'de> for SeqAccess<'de, 'a, R> {
     type Error = Error;
 
     fn size_hint(&self) -> Option<usize> {
@@ -63,10 +88,37 @@ impl<'de, 'a, R: 'a + BufRead> de::SeqAccess<'de> for SeqAccess<'a, R> {
             }
             *s -= 1;
         }
-        match self.de.peek()? {
-            None | Some(Event::Eof) | Some(Event::End(_)) => Ok(None),
-            Some(Event::Start(e)) if !self.names.is_valid(e) => Ok(None),
-            _ => seed.deserialize(&mut *self.de).map(Some),
+        // named elements are no text, otherwise whitespace-only text before the end of the
+        // sequence is an item only of sequences of text
+        if let Names::Peek(_) = self.names {
+            self.de.skip_whitespace()?;
+        } else if !self.text_items {
+            self.de.peek_after_whitespace()?;
+            let ends = match self.de.after_peek.as_ref().or(self.de.peek.as_ref()) {
+                None | Some(Event::Eof) | Some(Event::End(_)) => true,
+                Some(Event::Start(e)) => !self.names.is_valid(self.de, e),
+                _ => false,
+            };
+            if ends {
+                self.de.skip_whitespace()?;
+            }
+        }
+        if let None | Some(Event::Eof) | Some(Event::End(_)) = self.de.peek()? {
+            return Ok(None);
         }
+        if let Some(Event::Start(e)) = &self.de.peek {
+            if !self.names.is_valid(self.de, e) {
+                return Ok(None);
+            }
+        }
+        let text = match &self.de.peek {
+            Some(Event::CData(_)) => true,
+            Some(Event::Text(_)) => !self.de.peek_whitespace()?,
+            _ => false,
+        };
+        self.de.field_element = matches!(self.names, Names::Peek(_));
+        let item = seed.deserialize(&mut *self.de)?;
+        self.text_items |= text;
+        Ok(Some(item))
     }
 }
@@ -1,66 +1,153 @@
-use std::io::BufRead;
-
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use serde::de::{self, Deserializer as SerdeDeserializer};
 
-use crate::de::{escape::EscapedDeserializer, Deserializer};
+use crate::de::{Deserializer, XmlRead, TEXT};
 use crate::error::Reason;
 use crate::Error;
 
 /// An enum access
-pub struct EnumAccess<'a, R: BufRead> {
-    de: &'a mut Deserializer<R>,
+pub struct EnumAccess<'de, 'a, R: XmlRead<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+    variants: &'static [&'static str],
+    /// The next element is the element of a field
+    field: bool,
 }
 
-impl<'a, R: BufRead> EnumAccess<'a, R> {
-    pub fn new(de: &'a mut Deserializer<R>) -> Self {
-        EnumAccess { de }
+impl<'de, 'a, R: XmlRead<'de>> EnumAccess<'de, 'a, R> {
+    pub fn new(
+        de: &'a mut Deserializer<'de, R>,
+        variants: &'static [&'static str],
+        field: bool,
+    ) -> Self {
+        EnumAccess {
+            de,
+            variants,
+            field,
+        }
+    }
+
+    /// The element of a field, which is no variant name, is a wrapper around the variant:
+    /// `<field>Variant</field>` or `<field><Variant/></field>`
+    ///
+    /// Other elements are variants, so unknown names are unknown variants.
+    fn is_wrapper(&self, start: &BytesStart) -> bool {
+        let name = self.de.element_name(start);
+        self.field && !self.variants.iter().any(|v| v.as_bytes() == &*name)
     }
 }
 
-impl<'de, 'a, R: 'a + BufRead> de::EnumAccess<'de> for EnumAccess<'a, R> {
+impl<'de, 'a, R: 'a + XmlRead<'de>> de::EnumAccess<'de> for EnumAccess<'de, 'a, R> {
     type Error = Error;
-    type Variant = VariantAccess<'a, R>;
+    type Variant = VariantAccess<'de, 'a, R>;
 
     fn variant_seed<V: de::DeserializeSeed<'de>>(
         self,
         seed: V,
-    ) -> Result<(V::Value, VariantAccess<'a, R>), Error> {
-        let de = match self.de.peek()? {
-            Some(Event::Text(t)) => EscapedDeserializer::new(t.to_vec(), true),
-            Some(Event::Start(e)) => EscapedDeserializer::new(e.name().to_vec(), false),
+    ) -> Result<(V::Value, VariantAccess<'de, 'a, R>), Error> {
+        // whitespace around elements is only text of a text variant
+        let takes_text = self.variants.contains(&TEXT);
+        if !takes_text {
+            self.de.skip_whitespace()?;
+        }
+        self.de.peek()?;
+        let wrapper = match &self.de.peek {
+            Some(Event::Start(e)) if self.is_wrapper(e) => Some(e.name().to_vec()),
+            _ => None,
+        };
+        if wrapper.is_some() {
+            if let Event::Start(e) = self.de.next()? {
+                self.de.check_attributes(&e)?;
+            }
+            if !takes_text {
+                self.de.skip_whitespace()?;
+            }
+            self.de.peek()?;
+        }
+
+        let text = match &self.de.peek {
+            Some(Event::Text(_)) | Some(Event::CData(_)) => takes_text,
+            _ => false,
+        };
+        let de = match &self.de.peek {
+            // text in mixed content
+            Some(Event::Text(_)) | Some(Event::CData(_)) if text => {
+                self.de
+                    .escaped(TEXT.as_bytes().into(), false, self.de.reader.event_offset())
+            }
+            Some(Event::Text(t)) => {
+                let (name, offset) = self.de.text_content(t, self.de.reader.event_offset());
+                self.de.escaped(name, true, offset)
+            }
+            Some(Event::Start(e)) => self.de.escaped(
+                self.de.element_name(e).into_owned().into(),
+                false,
+                self.de.reader.event_offset(),
+            ),
             Some(e) => {
-                let event = e.to_owned();
+                let event = e.clone().into_owned();
                 return Err(self.de.peek_error(Reason::InvalidEnum(event)));
             }
             None => return Err(self.de.peek_error(Reason::Eof)),
         };
         let name = seed.deserialize(de)?;
-        Ok((name, VariantAccess { de: self.de }))
+        Ok((
+            name,
+            VariantAccess {
+                de: self.de,
+                wrapper,
+                text,
+            },
+        ))
     }
 }
 
-pub struct VariantAccess<'a, R: BufRead> {
-    de: &'a mut Deserializer<R>,
+pub struct VariantAccess<'de, 'a, R: XmlRead<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+    /// Name of the element around the variant
+    wrapper: Option<Vec<u8>>,
+    /// The variant is text in mixed content
+    text: bool,
 }
 
-impl<'de, 'a, R: BufRead> de::VariantAccess<'de> for VariantAccess<'a, R> {
+impl<'de, 'a, R: XmlRead<'de>> VariantAccess<'de, 'a, R> {
+    fn close<T>(self, value: T) -> Result<T, Error> {
+        if let Some(wrapper) = self.wrapper {
+            self.de.read_to_end(&wrapper)?;
+        }
+        Ok(value)
+    }
+}
+
+impl<'de, 'a, R: XmlRead<'de>> de::VariantAccess<'de> for VariantAccess<'de, 'a, R> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<(), Error> {
-        match self.de.next(&mut Vec::new())? {
-            Event::Start(e) => self.de.read_to_end(e.name()),
-            Event::Text(_) => Ok(()),
+        match self.de.next()? {
+            Event::Start(e) => {
+                self.de.check_attributes(&e)?;
+                self.de.read_to_end(e.name())?
+            }
+            Event::Text(_) | Event::CData(_) => (),
             _ => unreachable!(),
         }
+        self.close(())
     }
 
     fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
-        seed.deserialize(&mut *self.de)
+        if let Some(Event::Start(_)) = self.de.peek {
+            self.de.variant_start = Some(self.de.reader.event_offset());
+        }
+        // text in mixed content is kept as written
+        self.de.keep_text = self.text;
+        let value = seed.deserialize(&mut *self.de);
+        self.de.variant_start = None;
+        self.de.keep_text = false;
+        self.close(value?)
     }
 
     fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
-        self.de.deserialize_tuple(len, visitor)
+        let value = self.de.deserialize_tuple(len, visitor)?;
+        self.close(value)
     }
 
     fn struct_variant<V: de::Visitor<'de>>(
@@ -68,6 +155,7 @@ impl<'de, 'a, R: BufRead> de::VariantAccess<'de> for VariantAccess<'a, R> {
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Error> {
-        self.de.deserialize_struct("", fields, visitor)
+        let value = self.de.deserialize_struct("", fields, visitor)?;
+        self.close(value)
     }
 }
@@ -1,16 +1,188 @@
-use serde::de::{EnumAccess, Error, MapAccess, SeqAccess, Unexpected, Visitor};
-use serde::ser::{SerializeMap, SerializeStruct};
+//! Untyped XML element tree
+//!
+//! [`Element`] can hold any XML element with its attributes, text and children. It is useful for
+//! the schemaless parts of a document:
+//!
+//! ```
+//! use xserde::element::Element;
+//!
+//! let element: Element = xserde::from_str(r#"<root id="1">text<child/>tail</root>"#).unwrap();
+//! assert_eq!(element.tag(), "root");
+//! assert_eq!(element.attribute("id"), Some("1"));
+//! assert_eq!(element.text(), "text");
+//! assert_eq!(element.children()[0].tag(), "child");
+//! assert_eq!(element.children()[0].tail(), "tail");
+//!
+//! assert_eq!(
+//!     xserde::to_string(&element).unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8"?><root id="1">text<child/>tail</root>"#
+//! );
+//! ```
+
+use serde::de::{EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
 use std::fmt;
-use std::fmt::Formatter;
 
+use crate::de::{ANY_FIELDS, INNER_VALUE, TAG_NAME};
+use crate::Booleans;
+
+/// An untyped XML element
+///
+/// Text is stored like in the ElementTree API: `text` is the content before the first child and
+/// `tail` is the content after the end tag of the element up to the next sibling.
+///
+/// Values are stored as text, booleans from XML keep the literal as written. Booleans from other
+/// formats than XML are written with the [`Booleans`] of the [`Serializer`](crate::Serializer).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Element {
     tag: String,
     attributes: BTreeMap<String, String>,
     children: Vec<Element>,
     inner_prefix: String,
     outer_suffix: String,
+    /// Boolean from another format, which `inner_prefix` holds with the default literals
+    boolean: Option<bool>,
+}
+
+impl Element {
+    /// Create a new element without attributes and content
+    pub fn new<S: Into<String>>(tag: S) -> Self {
+        Element {
+            tag: tag.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Element without tag name, which only contains text
+    fn from_text(text: String) -> Self {
+        Element {
+            inner_prefix: text,
+            ..Default::default()
+        }
+    }
+
+    /// Element without tag name, which only contains a boolean
+    fn from_bool(value: bool) -> Self {
+        Element {
+            boolean: Some(value),
+            ..Element::from_text(Booleans::default().literal(value).to_string())
+        }
+    }
+
+    /// Add an attribute
+    pub fn with_attribute<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add a child element
+    pub fn with_child(mut self, child: Element) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Set text before the first child element
+    pub fn with_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    /// Set text after the end of the element
+    pub fn with_tail<S: Into<String>>(mut self, tail: S) -> Self {
+        self.outer_suffix = tail.into();
+        self
+    }
+
+    /// Tag name including namespace prefix
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Set the tag name including namespace prefix
+    pub fn set_tag<S: Into<String>>(&mut self, tag: S) {
+        self.tag = tag.into();
+    }
+
+    /// All attributes ordered by name
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// All attributes for modification
+    pub fn attributes_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.attributes
+    }
+
+    /// Value of the attribute with the name `name`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value as &str)
+    }
+
+    /// Set an attribute and return the old value
+    pub fn set_attribute<K: Into<String>, V: Into<String>>(
+        &mut self,
+        name: K,
+        value: V,
+    ) -> Option<String> {
+        self.attributes.insert(name.into(), value.into())
+    }
+
+    /// Remove an attribute and return its value
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        self.attributes.remove(name)
+    }
+
+    /// Child elements in document order
+    pub fn children(&self) -> &[Element] {
+        &self.children
+    }
+
+    /// Child elements in document order for modification
+    pub fn children_mut(&mut self) -> &mut Vec<Element> {
+        &mut self.children
+    }
+
+    /// Append a child element
+    pub fn push_child(&mut self, child: Element) {
+        self.children.push(child);
+    }
+
+    /// First child element with the tag name `tag`
+    pub fn child(&self, tag: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.tag == tag)
+    }
+
+    /// All child elements with the tag name `tag`
+    pub fn children_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.tag == tag)
+    }
+
+    /// Text before the first child element
+    pub fn text(&self) -> &str {
+        &self.inner_prefix
+    }
+
+    /// Set text before the first child element
+    pub fn set_text<S: Into<String>>(&mut self, text: S) {
+        self.inner_prefix = text.into();
+        self.boolean = None;
+    }
+
+    /// Text after the end of the element up to the next sibling
+    pub fn tail(&self) -> &str {
+        &self.outer_suffix
+    }
+
+    /// Set text after the end of the element
+    pub fn set_tail<S: Into<String>>(&mut self, tail: S) {
+        self.outer_suffix = tail.into();
+    }
+
+    fn is_text_only(&self) -> bool {
+        self.tag.is_empty() && self.attributes.is_empty() && self.children.is_empty()
+    }
 }
 
 impl Serialize for Element {
@@ -18,14 +190,32 @@ impl Serialize for Element {
     where
         S: Serializer,
     {
+        if self.is_text_only() {
+            return match self.boolean {
+                Some(value) => serializer.serialize_bool(value),
+                None => serializer.serialize_str(&self.inner_prefix),
+            };
+        }
+
         let mut map = serializer.serialize_map(None)?;
 
-        for attr in &self.attributes {
-            map.serialize_entry(&format!("@{}", attr.0), attr.1);
+        if !self.tag.is_empty() {
+            map.serialize_entry(TAG_NAME, &self.tag)?;
+        }
+
+        for (name, value) in &self.attributes {
+            map.serialize_entry(&format!("@{}", name), value)?;
+        }
+
+        if !self.inner_prefix.is_empty() {
+            map.serialize_entry(INNER_VALUE, &self.inner_prefix)?;
         }
 
         for child in &self.children {
-            map.serialize_entry(&child.tag, &child);
+            map.serialize_entry(&child.tag, &child)?;
+            if !child.outer_suffix.is_empty() {
+                map.serialize_entry(INNER_VALUE, &child.outer_suffix)?;
+            }
         }
 
         map.end()
@@ -46,162 +236,239 @@ impl<'de> Deserialize<'de> for Element {
                 formatter.write_str("any valid XML element value")
             }
 
+            // Only other formats produce booleans, the XML deserializer visits text
             fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: if v {
-                        "yes".to_string()
-                    } else {
-                        "no".to_string()
-                    },
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_bool(v))
             }
 
             fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: format!("{}", v),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v.to_string()))
             }
 
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: format!("{}", v),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v.to_string()))
             }
 
             fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: format!("{}", v),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v.to_string()))
             }
 
             fn visit_char<E>(self, v: char) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: v.to_string(),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v.to_string()))
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: v.to_string(),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v.to_string()))
             }
 
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: v,
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::from_text(v))
             }
 
             fn visit_none<E>(self) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: String::new(),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::default())
             }
 
             fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
             where
                 D: Deserializer<'de>,
             {
-                todo!()
+                Element::deserialize(deserializer)
             }
 
             fn visit_unit<E>(self) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                Ok(Element {
-                    tag: "".to_string(),
-                    attributes: Default::default(),
-                    children: vec![],
-                    inner_prefix: String::new(),
-                    outer_suffix: "".to_string(),
-                })
+                Ok(Element::default())
             }
 
             fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
             where
                 D: Deserializer<'de>,
             {
-                todo!()
+                Element::deserialize(deserializer)
             }
 
-            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
                 A: SeqAccess<'de>,
             {
-                todo!()
+                let mut element = Element::default();
+                while let Some(child) = seq.next_element()? {
+                    element.children.push(child);
+                }
+                Ok(element)
             }
 
-            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
             where
                 A: MapAccess<'de>,
             {
-                todo!()
+                let mut element = Element::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == TAG_NAME {
+                        element.tag = map.next_value()?;
+                    } else if key == INNER_VALUE {
+                        let text: String = map.next_value()?;
+                        match element.children.last_mut() {
+                            Some(child) => child.outer_suffix.push_str(&text),
+                            None => element.inner_prefix.push_str(&text),
+                        }
+                    } else if let Some(name) = key.strip_prefix('@') {
+                        element
+                            .attributes
+                            .insert(name.to_string(), map.next_value()?);
+                    } else {
+                        let mut child: Element = map.next_value()?;
+                        if child.tag.is_empty() {
+                            child.tag = key;
+                        }
+                        element.children.push(child);
+                    }
+                }
+                Ok(element)
             }
 
             fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
             where
                 A: EnumAccess<'de>,
             {
-                todo!()
+                let (tag, variant): (String, _) = data.variant()?;
+                let mut element: Element = variant.newtype_variant()?;
+                if element.tag.is_empty() {
+                    element.tag = tag;
+                }
+                Ok(element)
             }
         }
-        deserializer.deserialize_any(ElementVisitor)
+
+        deserializer.deserialize_struct("Element", &[TAG_NAME, ANY_FIELDS], ElementVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_string};
+
+    #[test]
+    fn roundtrip() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?><root a="1" b="&lt;2&gt;">text<child c="3">inner</child>tail<xsi:other/></root>"#;
+        let element: Element = from_str(xml).unwrap();
+
+        assert_eq!(
+            element,
+            Element::new("root")
+                .with_attribute("a", "1")
+                .with_attribute("b", "<2>")
+                .with_text("text")
+                .with_child(
+                    Element::new("child")
+                        .with_attribute("c", "3")
+                        .with_text("inner")
+                        .with_tail("tail")
+                )
+                .with_child(Element::new("xsi:other"))
+        );
+        assert_eq!(to_string(&element).unwrap(), xml);
+    }
+
+    #[test]
+    fn accessors() {
+        let element: Element =
+            from_str(r#"<root><item id="1"/><other/><item id="2">two</item></root>"#).unwrap();
+
+        assert_eq!(element.child("other").map(Element::tag), Some("other"));
+        assert_eq!(element.child("missing"), None);
+        assert_eq!(
+            element
+                .children_with_tag("item")
+                .map(|item| item.attribute("id").unwrap())
+                .collect::<Vec<_>>(),
+            vec!["1", "2"]
+        );
+        assert_eq!(element.children()[2].text(), "two");
+    }
+
+    #[test]
+    fn typed_field() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Document {
+            #[serde(rename = "@version")]
+            version: String,
+            extensions: Element,
+            name: String,
+        }
+
+        let xml = r#"<Document version="1"><extensions><any x="y">z</any></extensions><name>n</name></Document>"#;
+        let document: Document = from_str(xml).unwrap();
+
+        assert_eq!(
+            document.extensions,
+            Element::new("extensions")
+                .with_child(Element::new("any").with_attribute("x", "y").with_text("z"))
+        );
+        assert_eq!(crate::ser::fragment_to_string(&document).unwrap(), xml);
+    }
+
+    #[test]
+    fn boolean_from_other_format() {
+        let element = Element::deserialize(serde_value::Value::Bool(true)).unwrap();
+        assert_eq!(element.text(), "true");
+
+        let mut buffer = Vec::new();
+        let mut writer = crate::Writer::new(&mut buffer);
+        let mut ser = crate::Serializer::new_with_root(&mut writer, Some("flag"))
+            .with_xmldecl(false)
+            .with_booleans(Booleans::custom("Y", "N"));
+        element.serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "<flag>Y</flag>");
+
+        // changed text is no boolean anymore
+        let mut buffer = Vec::new();
+        let mut writer = crate::Writer::new(&mut buffer);
+        let mut ser = crate::Serializer::new_with_root(&mut writer, Some("flag"))
+            .with_xmldecl(false)
+            .with_booleans(Booleans::custom("Y", "N"));
+        element.with_text("true").serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "<flag>true</flag>");
+    }
+
+    #[test]
+    fn boolean_literal_from_xml() {
+        let options = crate::DeserializerOptions::new().with_booleans(Booleans::custom("Y", "N"));
+        let element: Element = options.from_str("<flag>Y</flag>").unwrap();
+        assert_eq!(element.text(), "Y");
+    }
+
+    #[test]
+    fn missing_tag() {
+        let element = Element::new("").with_attribute("a", "b");
+        assert!(to_string(&element).is_err());
     }
 }
@@ -0,0 +1,334 @@
+//! Character encodings of documents
+
+/// Characters of Windows-1252 for the bytes `0x80..=0x9F`, `None` for unused bytes
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('\u{20AC}'),
+    None,
+    Some('\u{201A}'),
+    Some('\u{0192}'),
+    Some('\u{201E}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02C6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017D}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201C}'),
+    Some('\u{201D}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02DC}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203A}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017E}'),
+    Some('\u{0178}'),
+];
+
+/// Character encoding of a document
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Encoding {
+    /// UTF-8
+    Utf8,
+    /// UTF-16 little endian, documents start with a byte order mark
+    Utf16Le,
+    /// UTF-16 big endian, documents start with a byte order mark
+    Utf16Be,
+    /// ISO-8859-1 (Latin-1)
+    Iso8859_1,
+    /// Windows-1252, a superset of the printable characters of ISO-8859-1
+    Windows1252,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Utf8
+    }
+}
+
+/// Value of `encoding` in the XML declaration at the start of `head`
+fn declared_encoding(head: &[u8]) -> Option<String> {
+    let decl = head.strip_prefix(b"<?xml")?;
+    let decl = &decl[..decl.windows(2).position(|w| w == b"?>")?];
+    let start = decl.windows(8).position(|w| w == b"encoding")? + 8;
+    let value = decl[start..]
+        .iter()
+        .skip_while(|&&b| b == b' ' || b == b'=' || b == b'\t' || b == b'\r' || b == b'\n');
+    let mut value = value.copied();
+    let quote = value.next().filter(|&q| q == b'"' || q == b'\'')?;
+    let name: Vec<u8> = value.take_while(|&b| b != quote).collect();
+    String::from_utf8(name).ok()
+}
+
+/// Part of serialized XML
+#[derive(Copy, Clone)]
+enum Markup {
+    Content,
+    Tag,
+    AttributeValue(char),
+}
+
+impl Encoding {
+    /// Name for the XML declaration
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Iso8859_1 => "ISO-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Byte order mark written at the start of a document
+    pub(crate) fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            _ => b"",
+        }
+    }
+
+    /// Encoding for a name of the XML declaration
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(Encoding::Utf8),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" => {
+                Some(Encoding::Iso8859_1)
+            }
+            "windows-1252" | "cp1252" | "x-cp1252" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    /// Detects the encoding from the byte order mark or the XML declaration at the start of
+    /// a document, UTF-8 if there is neither
+    ///
+    /// Returns the encoding and the length of the byte order mark.
+    pub(crate) fn detect(head: &[u8]) -> Result<(Encoding, usize), String> {
+        Ok(match head {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [b'<', 0, b'?', 0, ..] => (Encoding::Utf16Le, 0),
+            [0, b'<', 0, b'?', ..] => (Encoding::Utf16Be, 0),
+            _ => match declared_encoding(head) {
+                Some(name) => match Encoding::from_name(&name) {
+                    Some(encoding) => (encoding, 0),
+                    None => return Err(format!("unsupported encoding `{}`", name)),
+                },
+                None => (Encoding::Utf8, 0),
+            },
+        })
+    }
+
+    /// Length of the byte order mark of this encoding at the start of `head`
+    pub(crate) fn bom_len(self, head: &[u8]) -> usize {
+        match self {
+            Encoding::Utf8 if head.starts_with(b"\xEF\xBB\xBF") => 3,
+            Encoding::Utf16Le | Encoding::Utf16Be if head.starts_with(self.bom()) => 2,
+            _ => 0,
+        }
+    }
+
+    /// Decodes `bytes` into `out`
+    ///
+    /// Returns the number of decoded bytes, an incomplete character at the end is left.
+    pub(crate) fn decode(self, bytes: &[u8], out: &mut String) -> Result<usize, String> {
+        match self {
+            Encoding::Utf8 => {
+                let valid = match std::str::from_utf8(bytes) {
+                    Ok(valid) => valid,
+                    Err(err) if err.error_len().is_none() => {
+                        std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap()
+                    }
+                    Err(err) => return Err(err.to_string()),
+                };
+                out.push_str(valid);
+                Ok(valid.len())
+            }
+            Encoding::Iso8859_1 => {
+                out.extend(bytes.iter().map(|&b| b as char));
+                Ok(bytes.len())
+            }
+            Encoding::Windows1252 => {
+                out.extend(bytes.iter().map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252[b as usize - 0x80].unwrap_or(b as char),
+                    _ => b as char,
+                }));
+                Ok(bytes.len())
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|unit| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([unit[0], unit[1]]),
+                    _ => u16::from_be_bytes([unit[0], unit[1]]),
+                });
+                let mut decoded = 0;
+                for c in std::char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => {
+                            out.push(c);
+                            decoded += c.len_utf16() * 2;
+                        }
+                        // the low surrogate can be in the next chunk
+                        Err(err)
+                            if (0xD800..0xDC00).contains(&err.unpaired_surrogate())
+                                && decoded + 2 == bytes.len() / 2 * 2 =>
+                        {
+                            break
+                        }
+                        Err(err) => return Err(err.to_string()),
+                    }
+                }
+                Ok(decoded)
+            }
+        }
+    }
+
+    /// Single byte of `c`, if it can be encoded
+    fn encode_byte(self, c: char) -> Option<u8> {
+        match (self, c as u32) {
+            (Encoding::Iso8859_1, 0..=0xFF) => Some(c as u8),
+            (Encoding::Windows1252, 0..=0x7F) | (Encoding::Windows1252, 0xA0..=0xFF) => {
+                Some(c as u8)
+            }
+            (Encoding::Windows1252, _) => WINDOWS_1252
+                .iter()
+                .position(|&w| w == Some(c))
+                .map(|i| 0x80 + i as u8),
+            _ => None,
+        }
+    }
+
+    fn push(self, c: char, out: &mut Vec<u8>) -> bool {
+        let mut units = [0u16; 2];
+        match self {
+            Encoding::Utf8 => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            Encoding::Utf16Le => c
+                .encode_utf16(&mut units)
+                .iter()
+                .for_each(|u| out.extend_from_slice(&u.to_le_bytes())),
+            Encoding::Utf16Be => c
+                .encode_utf16(&mut units)
+                .iter()
+                .for_each(|u| out.extend_from_slice(&u.to_be_bytes())),
+            Encoding::Iso8859_1 | Encoding::Windows1252 => match self.encode_byte(c) {
+                Some(b) => out.push(b),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    /// Encodes serialized `xml`, which consists of complete tags and text
+    ///
+    /// Characters without representation are written as character references in text and
+    /// attribute values. Returns the character, when it is part of a name.
+    pub(crate) fn encode(self, xml: &str) -> Result<Vec<u8>, char> {
+        let mut out = Vec::with_capacity(xml.len());
+        let mut markup = Markup::Content;
+        for c in xml.chars() {
+            markup = match (markup, c) {
+                (Markup::Content, '<') => Markup::Tag,
+                (Markup::Tag, '>') => Markup::Content,
+                (Markup::Tag, '"') | (Markup::Tag, '\'') => Markup::AttributeValue(c),
+                (Markup::AttributeValue(quote), _) if c == quote => Markup::Tag,
+                (markup, _) => markup,
+            };
+            if !self.push(c, &mut out) {
+                if let Markup::Tag = markup {
+                    return Err(c);
+                }
+                for c in format!("&#x{:X};", c as u32).chars() {
+                    self.push(c, &mut out);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_byte() {
+        let xml = r#"<a b="€ ÿ ő">€ ÿ ő</a>"#;
+        assert_eq!(
+            Encoding::Iso8859_1.encode(xml).unwrap(),
+            b"<a b=\"&#x20AC; \xFF &#x151;\">&#x20AC; \xFF &#x151;</a>"
+        );
+        assert_eq!(
+            Encoding::Windows1252.encode(xml).unwrap(),
+            b"<a b=\"\x80 \xFF &#x151;\">\x80 \xFF &#x151;</a>"
+        );
+        assert_eq!(Encoding::Iso8859_1.encode("<ő/>"), Err('ő'));
+    }
+
+    #[test]
+    fn detect() {
+        let detect = |head: &[u8]| Encoding::detect(head).unwrap();
+        assert_eq!(detect(b"<root/>"), (Encoding::Utf8, 0));
+        assert_eq!(detect(b"\xEF\xBB\xBF<root/>"), (Encoding::Utf8, 3));
+        assert_eq!(detect(b"\xFF\xFE<\0"), (Encoding::Utf16Le, 2));
+        assert_eq!(detect(b"\0<\0?\0x"), (Encoding::Utf16Be, 0));
+        assert_eq!(
+            detect(b"<?xml version='1.0' encoding = 'ISO-8859-1'?><root/>"),
+            (Encoding::Iso8859_1, 0)
+        );
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\"?><root encoding=\"latin1\"/>"),
+            (Encoding::Utf8, 0)
+        );
+        assert!(Encoding::detect(b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?>").is_err());
+    }
+
+    #[test]
+    fn decode() {
+        let decode = |encoding: Encoding, bytes: &[u8]| {
+            let mut out = String::new();
+            let decoded = encoding.decode(bytes, &mut out).unwrap();
+            (out, decoded)
+        };
+        assert_eq!(
+            decode(Encoding::Windows1252, b"\x80\xFC\x81"),
+            ("€ü\u{81}".to_string(), 3)
+        );
+        assert_eq!(decode(Encoding::Utf8, b"a\xC3"), ("a".to_string(), 1));
+        // incomplete unit and surrogate pair are left for the next chunk
+        assert_eq!(
+            decode(Encoding::Utf16Be, b"\0a\xD8\x34\xDD"),
+            ("a".to_string(), 2)
+        );
+        assert_eq!(
+            decode(Encoding::Utf16Be, b"\xD8\x34\xDD\x1E"),
+            ("𝄞".to_string(), 4)
+        );
+        assert!(Encoding::Utf16Le
+            .decode(b"\x34\xD8a\0", &mut String::new())
+            .is_err());
+    }
+
+    #[test]
+    fn utf16() {
+        assert_eq!(Encoding::Utf16Le.encode("<a>€</a>").unwrap(), {
+            let mut expected = Vec::new();
+            for u in "<a>€</a>".encode_utf16() {
+                expected.extend_from_slice(&u.to_le_bytes());
+            }
+            expected
+        });
+        assert_eq!(Encoding::Utf16Be.encode("𝄞").unwrap(), b"\xD8\x34\xDD\x1E");
+    }
+}
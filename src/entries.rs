@@ -0,0 +1,71 @@
+//! Maps written as `<entry key="k">value</entry>` elements instead of `<k>value</k>`
+//!
+//! Use this module with `#[serde(with = "xserde::entries")]` on a map field. This works for any
+//! key, also for keys which are no valid XML names. The element and attribute names are `entry`
+//! and `key`, unless they are changed with `with_entry_names` on
+//! [`Serializer`](crate::Serializer::with_entry_names) and
+//! [`DeserializerOptions`](crate::DeserializerOptions::with_entry_names).
+//!
+//! Other elements, text and attributes of the map element are skipped when deserializing. Serde
+//! buffers the content of structures with flattened fields, so such a field cannot be read from a
+//! structure with flattened fields.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! struct Prices {
+//!     #[serde(with = "xserde::entries")]
+//!     items: BTreeMap<String, u32>,
+//! }
+//!
+//! let xml = r#"<Prices><items><entry key="apple">2</entry></items></Prices>"#;
+//! let prices: Prices = xserde::from_str(xml).unwrap();
+//! assert_eq!(prices.items["apple"], 2);
+//! assert_eq!(xserde::ser::fragment_to_string(&prices).unwrap(), xml);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Name of the newtype structure, which marks a map written as entries
+pub(crate) const MAP_ENTRIES: &str = "$entries";
+/// Default element name for map entries
+pub(crate) const ENTRY: &str = "entry";
+/// Default attribute name for the key of map entries
+pub(crate) const ENTRY_KEY: &str = "key";
+
+/// Serializes `map` as entries
+pub fn serialize<M: ?Sized + Serialize, S: Serializer>(
+    map: &M,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(MAP_ENTRIES, map)
+}
+
+/// Deserializes a map from entries
+pub fn deserialize<'de, M: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<M, D::Error> {
+    deserializer.deserialize_newtype_struct(MAP_ENTRIES, EntriesVisitor(PhantomData))
+}
+
+struct EntriesVisitor<M>(PhantomData<M>);
+
+impl<'de, M: Deserialize<'de>> de::Visitor<'de> for EntriesVisitor<M> {
+    type Value = M;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<M, D::Error> {
+        M::deserialize(deserializer)
+    }
+}
@@ -4,6 +4,8 @@ use std::result::Result as StdResult;
 use quick_xml::Error as XmlError;
 use std::num::{ParseFloatError, ParseIntError};
 
+use crate::{BinaryEncoding, Encoding};
+
 /// Alias for a `Result` with the error type `serde_explicit_xml::Error`.
 pub type Result<T> = StdResult<T, Error>;
 
@@ -12,25 +14,184 @@ pub struct Error(Box<ErrorImpl>);
 
 impl Error {
     pub(crate) fn new(reason: Reason, offset: usize) -> Self {
-        Self(Box::new(ErrorImpl { offset, reason }))
+        Self(Box::new(ErrorImpl {
+            offset,
+            line: 0,
+            column: 0,
+            path: None,
+            reason,
+        }))
     }
 
-    pub(crate) fn fix_position(self, f: impl FnOnce(Reason) -> Error) -> Self {
-        if self.0.offset == 0 {
-            f(self.0.reason)
-        } else {
-            self
+    /// Set the element path, when the error has no path yet
+    pub(crate) fn fix_path(mut self, path: impl FnOnce() -> String) -> Self {
+        if self.0.path.is_none() {
+            self.0.path = Some(path()).filter(|path| !path.is_empty());
+        }
+        self
+    }
+
+    /// Set the offset of the value, which caused the error, when the error has no location yet
+    pub(crate) fn fix_offset(mut self, offset: usize) -> Self {
+        if self.0.line == 0 && self.0.offset == 0 {
+            self.0.offset = offset;
+        }
+        self
+    }
+
+    /// Set line and column, when the error has no location yet
+    ///
+    /// Errors without offset get the offset from `offset`.
+    pub(crate) fn fix_position(
+        mut self,
+        offset: impl FnOnce() -> usize,
+        location: impl FnOnce(usize) -> (usize, usize),
+    ) -> Self {
+        if self.0.line == 0 {
+            if self.0.offset == 0 {
+                self.0.offset = offset();
+            }
+            let (line, column) = location(self.0.offset);
+            self.0.line = line;
+            self.0.column = column;
+        }
+        self
+    }
+
+    /// Prepend the element or attribute `name` to the path of an invalid character or name
+    ///
+    /// Serialization errors get their path while they are passed up to the root element.
+    pub(crate) fn within(mut self, name: &str) -> Self {
+        if !matches!(
+            self.0.reason,
+            Reason::InvalidChar(_) | Reason::InvalidName(_)
+        ) {
+            return self;
         }
+        self.0.path = Some(match self.0.path.take() {
+            Some(path) => format!("{}/{}", name, path),
+            None => name.to_string(),
+        });
+        self
     }
 
+    /// Byte offset in the input, 0 for serialization errors
     pub fn offset(&self) -> usize {
         self.0.offset
     }
+
+    /// Line in the input starting at 1, or 0 when unknown
+    ///
+    /// Serialization errors have no line and column, only a [`path`](Self::path) for invalid
+    /// characters and names.
+    pub fn line(&self) -> usize {
+        self.0.line
+    }
+
+    /// Column in bytes starting at 1, or 0 when unknown
+    pub fn column(&self) -> usize {
+        self.0.column
+    }
+
+    /// Kind of the error
+    pub fn kind(&self) -> ErrorKind {
+        match &self.0.reason {
+            Reason::Xml(XmlError::Io(err)) if err.kind() == std::io::ErrorKind::InvalidData => {
+                ErrorKind::Syntax
+            }
+            Reason::Xml(XmlError::Io(_)) => ErrorKind::Io,
+            Reason::Xml(XmlError::UnexpectedEof(_)) | Reason::Eof => ErrorKind::Eof,
+            Reason::Xml(_) => ErrorKind::Syntax,
+            Reason::Message(_) => ErrorKind::Custom,
+            Reason::Int(_)
+            | Reason::Float(_)
+            | Reason::InvalidBoolean(_)
+            | Reason::InvalidBinary(..)
+            | Reason::Unencodable(..)
+            | Reason::InvalidUnit(_) => ErrorKind::InvalidValue,
+            Reason::InvalidChar(_) => ErrorKind::InvalidChar,
+            Reason::InvalidName(_) => ErrorKind::InvalidName,
+            Reason::MaxDepth(_) => ErrorKind::Limit,
+            Reason::UnknownElement(_) | Reason::UnknownAttribute(_) => ErrorKind::Unknown,
+            Reason::EndOfAttributes | Reason::InvalidEnum(_) | Reason::Start | Reason::End => {
+                ErrorKind::UnexpectedEvent
+            }
+            Reason::Unsupported(_) => ErrorKind::Unsupported,
+        }
+    }
+
+    /// Reading or writing the underlying stream failed
+    pub fn is_io(&self) -> bool {
+        self.kind() == ErrorKind::Io
+    }
+
+    /// The input is no well-formed XML
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    /// The input is well-formed, but does not match the data type
+    pub fn is_data(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::Custom
+                | ErrorKind::InvalidValue
+                | ErrorKind::UnexpectedEvent
+                | ErrorKind::Unknown
+        )
+    }
+
+    /// The input ended unexpectedly
+    pub fn is_eof(&self) -> bool {
+        self.kind() == ErrorKind::Eof
+    }
+
+    /// Path of the elements and the attribute, where the error occurred
+    ///
+    /// Elements after the root element have their index among the siblings with the same name:
+    /// `gpx/trk[2]/trkseg[0]/trkpt[17]/@lat`.
+    pub fn path(&self) -> Option<&str> {
+        self.0.path.as_deref()
+    }
+}
+
+/// Kind of an [`Error`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// I/O error of the underlying reader or writer
+    Io,
+    /// XML syntax error, including invalid UTF-8, escapes and unsupported encodings
+    Syntax,
+    /// Unexpected end of the input
+    Eof,
+    /// Error raised by a `Serialize` or `Deserialize` implementation, e.g. a missing field
+    Custom,
+    /// Text or attribute value, which cannot be parsed as the expected type
+    InvalidValue,
+    /// Element or text where something else was expected
+    UnexpectedEvent,
+    /// Data type is not supported
+    Unsupported,
+    /// Serialized text contains a character, which is not allowed in XML
+    InvalidChar,
+    /// Serialized map key is no valid XML name
+    InvalidName,
+    /// Input exceeds a limit of the deserializer, like the maximum depth
+    ///
+    /// The input may be valid, so this is neither a syntax nor a data error:
+    /// [`Error::is_syntax`] and [`Error::is_data`] return `false`.
+    Limit,
+    /// Element or attribute, which the data type does not have
+    Unknown,
 }
 
 /// struct to reduce size of `Error`
 struct ErrorImpl {
     offset: usize,
+    line: usize,
+    column: usize,
+    path: Option<String>,
     reason: Reason,
 }
 
@@ -49,12 +210,24 @@ pub(crate) enum Reason {
     Eof,
     /// Invalid value for a boolean
     InvalidBoolean(String),
+    /// Invalid encoded binary data
+    InvalidBinary(BinaryEncoding, String),
+    /// Character of a name, which cannot be represented in the output encoding
+    Unencodable(char, Encoding),
+    /// Character, which is not allowed in XML
+    InvalidChar(char),
+    /// Map key, which is no valid XML name
+    InvalidName(String),
     /// Invalid unit value
     InvalidUnit(String),
+    /// Elements are nested deeper than allowed
+    MaxDepth(usize),
+    /// Element, which is no field
+    UnknownElement(String),
+    /// Attribute, which is no field
+    UnknownAttribute(String),
     /// Invalid event for Enum
     InvalidEnum(quick_xml::events::Event<'static>),
-    /// Expecting Text event
-    Text,
     /// Expecting Start event
     Start,
     /// Expecting End event
@@ -68,6 +241,9 @@ impl fmt::Display for Reason {
         match self {
             Reason::Message(s) => write!(f, "{}", s),
             Reason::Xml(e) => match e {
+                XmlError::Io(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                    write!(f, "Encoding error: {}", err)
+                }
                 XmlError::Io(err) => write!(f, "I/O error: {}", err),
                 XmlError::Utf8(err) => write!(f, "UTF-8 error: {}", err),
                 _ => write!(f, "XML syntax error: {}", e),
@@ -77,6 +253,26 @@ impl fmt::Display for Reason {
             Reason::EndOfAttributes => write!(f, "Unexpected end of attributes"),
             Reason::Eof => write!(f, "Unexpected end of file"),
             Reason::InvalidBoolean(v) => write!(f, "Invalid boolean value '{}'", v),
+            Reason::InvalidBinary(encoding, v) => {
+                let encoding = match encoding {
+                    BinaryEncoding::Base64 => "base64",
+                    BinaryEncoding::Hex => "hex",
+                };
+                write!(f, "Invalid {} value '{}'", encoding, v)
+            }
+            Reason::Unencodable(c, encoding) => write!(
+                f,
+                "Character '{}' of a name cannot be encoded in {}",
+                c,
+                encoding.name()
+            ),
+            Reason::InvalidChar(c) => {
+                write!(f, "Character U+{:04X} is not allowed in XML", *c as u32)
+            }
+            Reason::InvalidName(name) => write!(f, "Invalid XML name `{}`", name),
+            Reason::MaxDepth(max) => write!(f, "Elements nested deeper than {} levels", max),
+            Reason::UnknownElement(name) => write!(f, "Unknown element `{}`", name),
+            Reason::UnknownAttribute(name) => write!(f, "Unknown attribute `{}`", name),
             Reason::InvalidUnit(v) => {
                 write!(f, "Invalid unit value '{}', expected empty string", v)
             }
@@ -85,7 +281,6 @@ impl fmt::Display for Reason {
                 "Invalid event for Enum, expecting Text or Start, got: {:?}",
                 e
             ),
-            Reason::Text => write!(f, "Expecting Text event"),
             Reason::Start => write!(f, "Expecting Start event"),
             Reason::End => write!(f, "Expecting End event"),
             Reason::Unsupported(s) => write!(f, "Unsupported operation: {}", s),
@@ -95,10 +290,16 @@ impl fmt::Display for Reason {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.0.offset == 0 {
-            write!(f, "{}", self.0.reason)
+        write!(f, "{}", self.0.reason)?;
+        if let Some(path) = &self.0.path {
+            write!(f, " in {}", path)?;
+        }
+        if self.0.line != 0 {
+            write!(f, " at {}:{}", self.0.line, self.0.column)
+        } else if self.0.offset != 0 {
+            write!(f, " at offset {}", self.0.offset)
         } else {
-            write!(f, "{} at offset {}", self.0.reason, self.0.offset)
+            Ok(())
         }
     }
 }
@@ -108,6 +309,9 @@ impl fmt::Debug for Error {
         f.debug_struct("Error")
             .field("message", &self.0.reason.to_string())
             .field("offset", &self.0.offset)
+            .field("line", &self.0.line)
+            .field("column", &self.0.column)
+            .field("path", &self.0.path)
             .finish()
     }
 }
@@ -125,14 +329,14 @@ impl ::std::error::Error for Error {
 
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        // TODO: parse error message for offset
+        // position is set by the `Deserializer`
         Error::new(Reason::Message(msg.to_string()), 0)
     }
 }
 
 impl serde::ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        // TODO: parse error message for offset
+        // the output has no location
         Error::new(Reason::Message(msg.to_string()), 0)
     }
 }
@@ -166,3 +370,77 @@ impl<T> ResultExt<T> for StdResult<T, ParseFloatError> {
         self.map_err(|err| Error::new(Reason::Float(err), offset))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead, Read};
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{from_reader, from_str};
+
+    #[derive(Debug, Deserialize)]
+    struct Item {
+        #[serde(rename = "@id")]
+        _id: u32,
+    }
+
+    /// Reader, which fails after the first chunk
+    struct FailingReader(&'static [u8]);
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            unreachable!()
+        }
+    }
+
+    impl BufRead for FailingReader {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.0.is_empty() {
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+            } else {
+                Ok(self.0)
+            }
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.0 = &self.0[amt..];
+        }
+    }
+
+    #[test]
+    fn kinds() {
+        let err = from_str::<Item>(r#"<item id="x"/>"#).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+        assert!(err.is_data());
+
+        let err = from_str::<Item>(r#"<item/>"#).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Custom);
+        assert!(err.is_data());
+
+        let err = from_str::<Item>(r#"<item id="1"></other>"#).unwrap_err();
+        assert!(err.is_syntax());
+        assert!(!err.is_data());
+
+        let err = from_str::<Item>(r#"<item id="1">"#).unwrap_err();
+        assert!(err.is_eof());
+
+        let err = from_reader::<_, Item>(FailingReader(b"<item id=")).unwrap_err();
+        assert!(err.is_io());
+
+        let err = crate::DeserializerOptions::new()
+            .with_max_depth(1)
+            .from_str::<Item>(r#"<item id="1"><a/></item>"#)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Limit);
+        assert!(!err.is_syntax() && !err.is_data());
+
+        let err = crate::DeserializerOptions::new()
+            .with_unknown_fields(crate::UnknownFields::Error)
+            .from_str::<Item>(r#"<item id="1" name="a"/>"#)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unknown);
+        assert!(err.is_data());
+    }
+}
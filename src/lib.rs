@@ -1,14 +1,30 @@
+mod binary;
+mod boolean;
+mod chars;
+pub mod children;
 pub mod de;
 pub mod element;
+mod encoding;
+pub mod entries;
 mod error;
+pub mod prolog;
 pub mod ser;
 
-pub use crate::de::{from_reader, from_str, Deserializer};
-pub use crate::error::{Error, Result};
+pub use crate::binary::BinaryEncoding;
+pub use crate::boolean::Booleans;
+pub use crate::chars::{InvalidChars, InvalidNames};
+pub use crate::de::{
+    from_reader, from_slice, from_str, Deserializer, DeserializerOptions, Skipped, UnknownFields,
+};
+pub use crate::encoding::Encoding;
+pub use crate::error::{Error, ErrorKind, Result};
 pub use crate::ser::{to_string, to_writer, Serializer};
 pub use quick_xml::{self, Writer};
 use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::fmt::Display;
+
+/// Namespace of the `xml` prefix, which is bound by definition
+pub(crate) const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
 
 /// Extensible Markup Language (XML) Version
 #[allow(non_camel_case_types)]
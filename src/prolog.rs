@@ -0,0 +1,343 @@
+//! XML declaration, document type declaration and processing instructions before the root
+//! element
+//!
+//! The prolog of a document is available from [`Deserializer::prolog`] and can be written with
+//! [`Serializer::with_prolog`]:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use xserde::{Deserializer, Serializer};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! #[serde(rename = "catalog")]
+//! struct Catalog {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//! }
+//!
+//! let xml = r#"<?xml version="1.0" encoding="UTF-8"?><?xml-stylesheet type="text/xsl" href="catalog.xsl"?><!DOCTYPE catalog SYSTEM "catalog.dtd"><catalog id="1"/>"#;
+//!
+//! let mut de = Deserializer::from_str(xml);
+//! let catalog = Catalog::deserialize(&mut de).unwrap();
+//! let prolog = de.prolog();
+//! assert_eq!(prolog.doctype().unwrap().system_id(), Some("catalog.dtd"));
+//! assert_eq!(prolog.instructions()[0].target(), "xml-stylesheet");
+//!
+//! let mut buffer = Vec::new();
+//! let mut writer = xserde::Writer::new(&mut buffer);
+//! let mut ser = Serializer::new(&mut writer).with_prolog(prolog);
+//! catalog.serialize(&mut ser).unwrap();
+//! assert_eq!(String::from_utf8(buffer).unwrap(), xml);
+//! ```
+//!
+//! [`Deserializer::prolog`]: crate::Deserializer::prolog
+//! [`Serializer::with_prolog`]: crate::Serializer::with_prolog
+
+use std::fmt;
+
+use quick_xml::events::BytesDecl;
+
+use crate::XmlVersion;
+
+/// Content of a document before the root element
+///
+/// Comments are not kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prolog {
+    version: Option<XmlVersion>,
+    encoding: Option<String>,
+    standalone: Option<bool>,
+    doctype: Option<DocType>,
+    instructions: Vec<ProcessingInstruction>,
+}
+
+impl Prolog {
+    /// Create an empty prolog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `standalone` of the XML declaration
+    pub fn with_standalone(mut self, standalone: bool) -> Self {
+        self.standalone = Some(standalone);
+        self
+    }
+
+    /// Set the document type declaration
+    pub fn with_doctype(mut self, doctype: DocType) -> Self {
+        self.doctype = Some(doctype);
+        self
+    }
+
+    /// Add a processing instruction
+    pub fn with_instruction(mut self, instruction: ProcessingInstruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Version of the XML declaration, `None` without declaration
+    pub fn version(&self) -> Option<XmlVersion> {
+        self.version
+    }
+
+    /// Encoding as named in the XML declaration
+    pub fn encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
+    /// `standalone` of the XML declaration
+    pub fn standalone(&self) -> Option<bool> {
+        self.standalone
+    }
+
+    /// Document type declaration
+    pub fn doctype(&self) -> Option<&DocType> {
+        self.doctype.as_ref()
+    }
+
+    /// Processing instructions in document order
+    pub fn instructions(&self) -> &[ProcessingInstruction] {
+        &self.instructions
+    }
+
+    pub(crate) fn set_declaration(&mut self, decl: &BytesDecl) -> quick_xml::Result<()> {
+        self.version = Some(match &*decl.version()? {
+            b"1.1" => XmlVersion::v1_1,
+            _ => XmlVersion::v1_0,
+        });
+        if let Some(encoding) = decl.encoding() {
+            self.encoding = Some(String::from_utf8_lossy(&encoding?).into_owned());
+        }
+        if let Some(standalone) = decl.standalone() {
+            self.standalone = Some(&*standalone? == b"yes");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_doctype(&mut self, content: &[u8]) {
+        self.doctype = Some(DocType::parse(&String::from_utf8_lossy(content)));
+    }
+
+    pub(crate) fn push_instruction(&mut self, content: &[u8]) {
+        let content = String::from_utf8_lossy(content);
+        let (target, data) = match content.find(char::is_whitespace) {
+            Some(end) => (&content[..end], content[end..].trim_start()),
+            None => (&*content, ""),
+        };
+        self.instructions
+            .push(ProcessingInstruction::new(target, data));
+    }
+}
+
+/// Document type declaration: `<!DOCTYPE name PUBLIC "public id" "system id" [subset]>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocType {
+    name: String,
+    public_id: Option<String>,
+    system_id: Option<String>,
+    internal_subset: Option<String>,
+}
+
+impl DocType {
+    /// Document type declaration for the root element `name`
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        DocType {
+            name: name.into(),
+            public_id: None,
+            system_id: None,
+            internal_subset: None,
+        }
+    }
+
+    /// Set the public and system identifier of the external subset
+    pub fn with_public_id<P: Into<String>, S: Into<String>>(
+        mut self,
+        public_id: P,
+        system_id: S,
+    ) -> Self {
+        self.public_id = Some(public_id.into());
+        self.system_id = Some(system_id.into());
+        self
+    }
+
+    /// Set the system identifier of the external subset
+    pub fn with_system_id<S: Into<String>>(mut self, system_id: S) -> Self {
+        self.system_id = Some(system_id.into());
+        self
+    }
+
+    /// Set the internal subset, the declarations between `[` and `]`
+    pub fn with_internal_subset<S: Into<String>>(mut self, internal_subset: S) -> Self {
+        self.internal_subset = Some(internal_subset.into());
+        self
+    }
+
+    /// Name of the root element
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Public identifier
+    pub fn public_id(&self) -> Option<&str> {
+        self.public_id.as_deref()
+    }
+
+    /// System identifier, usually the URL of the DTD
+    pub fn system_id(&self) -> Option<&str> {
+        self.system_id.as_deref()
+    }
+
+    /// Internal subset without the brackets
+    pub fn internal_subset(&self) -> Option<&str> {
+        self.internal_subset.as_deref()
+    }
+
+    /// Parses the content of `<!DOCTYPE` up to `>`
+    fn parse(content: &str) -> Self {
+        let content = content.trim_start();
+        let end = content
+            .find(|c: char| c.is_whitespace() || c == '[')
+            .unwrap_or(content.len());
+        let mut doctype = DocType::new(&content[..end]);
+        let mut rest = content[end..].trim_start();
+
+        let mut literals = 0;
+        if let Some(external) = rest.strip_prefix("PUBLIC") {
+            rest = external;
+            literals = 2;
+        } else if let Some(external) = rest.strip_prefix("SYSTEM") {
+            rest = external;
+            literals = 1;
+        }
+        let mut ids = Vec::new();
+        for _ in 0..literals {
+            rest = rest.trim_start();
+            let quote = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => break,
+            };
+            let end = rest[1..].find(quote).map_or(rest.len(), |end| end + 1);
+            ids.push(rest[1..end].to_string());
+            rest = rest.get(end + 1..).unwrap_or("");
+        }
+        let mut ids = ids.into_iter();
+        if literals == 2 {
+            doctype.public_id = ids.next();
+        }
+        doctype.system_id = ids.next();
+
+        let rest = rest.trim();
+        if let Some(subset) = rest.strip_prefix('[') {
+            doctype.internal_subset = Some(subset.strip_suffix(']').unwrap_or(subset).to_string());
+        }
+        doctype
+    }
+}
+
+impl fmt::Display for DocType {
+    /// Content of the declaration after `<!DOCTYPE`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, " {}", self.name)?;
+        match (&self.public_id, &self.system_id) {
+            (Some(public_id), Some(system_id)) => {
+                write!(f, " PUBLIC {} {}", quote(public_id), quote(system_id))?
+            }
+            (None, Some(system_id)) => write!(f, " SYSTEM {}", quote(system_id))?,
+            _ => (),
+        }
+        if let Some(subset) = &self.internal_subset {
+            write!(f, " [{}]", subset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Literal in quotes, which are not part of it
+fn quote(literal: &str) -> String {
+    if literal.contains('"') {
+        format!("'{}'", literal)
+    } else {
+        format!("\"{}\"", literal)
+    }
+}
+
+/// Processing instruction: `<?target data?>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessingInstruction {
+    target: String,
+    data: String,
+}
+
+impl ProcessingInstruction {
+    /// Create a processing instruction for the application `target`
+    pub fn new<T: Into<String>, D: Into<String>>(target: T, data: D) -> Self {
+        ProcessingInstruction {
+            target: target.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Application the instruction is directed to
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Content after the target, e.g. pseudo-attributes like `href="style.xsl"`
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+impl fmt::Display for ProcessingInstruction {
+    /// Content between `<?` and `?>`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.data.is_empty() {
+            f.write_str(&self.target)
+        } else {
+            write!(f, "{} {}", self.target, self.data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doctype() {
+        assert_eq!(DocType::parse(" html"), DocType::new("html"));
+        let xhtml = DocType::parse(
+            " html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\"\n  'http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd'",
+        );
+        assert_eq!(
+            xhtml,
+            DocType::new("html").with_public_id(
+                "-//W3C//DTD XHTML 1.0 Strict//EN",
+                "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"
+            )
+        );
+        let subset = DocType::parse(" note SYSTEM \"note.dtd\" [<!ENTITY a \"b\">]");
+        assert_eq!(subset.system_id(), Some("note.dtd"));
+        assert_eq!(subset.internal_subset(), Some("<!ENTITY a \"b\">"));
+        assert_eq!(
+            subset.to_string(),
+            " note SYSTEM \"note.dtd\" [<!ENTITY a \"b\">]"
+        );
+        assert_eq!(DocType::parse(" root[]").internal_subset(), Some(""));
+    }
+
+    #[test]
+    fn instruction() {
+        let mut prolog = Prolog::new();
+        prolog.push_instruction(b"xml-stylesheet  href=\"a.xsl\"");
+        prolog.push_instruction(b"empty");
+        assert_eq!(
+            prolog.instructions(),
+            &[
+                ProcessingInstruction::new("xml-stylesheet", "href=\"a.xsl\""),
+                ProcessingInstruction::new("empty", "")
+            ]
+        );
+        assert_eq!(prolog.instructions()[1].to_string(), "empty");
+    }
+}
@@ -1,19 +1,22 @@
 use std::fmt::Display;
 
-use serde::{serde_if_integer128, Serialize};
+use serde::Serialize;
 
 use crate::ser::nonser::NonSerializer;
+use crate::ser::Settings;
 use crate::Error;
 
-pub struct AttributeSerializer;
+pub struct AttributeSerializer<'s> {
+    settings: &'s Settings,
+}
 
-impl AttributeSerializer {
-    pub fn new() -> Self {
-        Self
+impl<'s> AttributeSerializer<'s> {
+    pub(crate) fn new(settings: &'s Settings) -> Self {
+        Self { settings }
     }
 }
 
-impl<'a> serde::Serializer for &'a mut AttributeSerializer {
+impl serde::Serializer for &mut AttributeSerializer<'_> {
     type Ok = Option<String>;
     type Error = Error;
     type SerializeSeq = NonSerializer<Self::Ok, Self::Error>;
@@ -25,7 +28,7 @@ impl<'a> serde::Serializer for &'a mut AttributeSerializer {
     type SerializeStructVariant = NonSerializer<Self::Ok, Self::Error>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        Ok(Some(if v { "true" } else { "false" }.to_string()))
+        Ok(Some(self.settings.booleans.literal(v).to_string()))
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
@@ -60,14 +63,12 @@ impl<'a> serde::Serializer for &'a mut AttributeSerializer {
         Ok(Some(v.to_string()))
     }
 
-    serde_if_integer128! {
-        fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
-            Ok(Some(v.to_string()))
-        }
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
 
-        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
-            Ok(Some(v.to_string()))
-        }
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
@@ -86,8 +87,8 @@ impl<'a> serde::Serializer for &'a mut AttributeSerializer {
         Ok(Some(v.to_string()))
     }
 
-    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(self.settings.binary_encoding.encode(v)))
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
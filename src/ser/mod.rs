@@ -1,21 +1,36 @@
 //! Module to handle custom serde `Serializer`
 
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::io::Write;
+use std::rc::Rc;
 
 use quick_xml::{
-    events::{BytesEnd, BytesStart, BytesText, Event},
+    events::{attributes::Attribute, BytesDecl, BytesEnd, BytesStart, BytesText, Event},
     Writer,
 };
 use serde::ser::{self, Serialize};
-use serde::serde_if_integer128;
 
-use crate::{Error, XmlVersion};
+use crate::chars;
+use crate::prolog::{DocType, ProcessingInstruction, Prolog};
+use crate::{
+    BinaryEncoding, Booleans, Encoding, Error, InvalidChars, InvalidNames, XmlVersion,
+    XML_NAMESPACE,
+};
 
+pub use self::stream::ElementStream;
 use self::var::{Seq, Struct};
+use crate::de::TEXT;
+use crate::entries::MAP_ENTRIES;
 use crate::error::Reason;
 
+// keep the signatures of the `serde::ser` traits with `T: ?Sized` and `where T: Serialize`
+#[allow(clippy::multiple_bound_locations)]
 mod attributes;
+#[allow(clippy::multiple_bound_locations)]
 mod nonser;
+mod stream;
+#[allow(clippy::multiple_bound_locations)]
 mod var;
 
 /// Serialize struct into a `Write`r
@@ -48,6 +63,79 @@ pub fn fragment_to_string<S: Serialize>(value: &S) -> Result<String, Error> {
     Ok(s)
 }
 
+/// Settings which are shared with the serializers of nested elements
+#[derive(Clone, Default)]
+pub(crate) struct Settings {
+    /// Registered namespaces as pairs of prefix and URI
+    namespaces: Vec<(String, String)>,
+    /// Encoding of bytes
+    binary_encoding: BinaryEncoding,
+    /// Character and number of characters per level for pretty-printing
+    indent: Option<(u8, usize)>,
+    /// Encoding of the output
+    encoding: Encoding,
+    /// Value of `standalone` in the XML declaration
+    standalone: Option<bool>,
+    /// Document type declaration after the XML declaration
+    doctype: Option<DocType>,
+    /// Processing instructions after the XML declaration
+    instructions: Vec<ProcessingInstruction>,
+    /// Handling of characters, which are not allowed in XML
+    invalid_chars: InvalidChars,
+    /// Handling of map keys, which are no valid XML names
+    pub(crate) invalid_names: InvalidNames,
+    /// Element and attribute name for writing map entries as `<entry key="k">value</entry>`
+    pub(crate) entry_names: Option<(String, String)>,
+    /// Literals for booleans
+    pub(crate) booleans: Booleans,
+}
+
+impl Settings {
+    /// Replaces a name in the form `{uri}local` with `prefix:local` using the registered prefix
+    fn resolve_name<'n>(&self, name: &'n str, attribute: bool) -> Result<Cow<'n, str>, Reason> {
+        let expanded_name = match name.strip_prefix('{') {
+            Some(expanded_name) => expanded_name,
+            None => return Ok(Cow::Borrowed(name)),
+        };
+        let end = expanded_name
+            .find('}')
+            .ok_or_else(|| Reason::Message(format!("missing `}}` in expanded name `{}`", name)))?;
+        let (uri, local_name) = (&expanded_name[..end], &expanded_name[end + 1..]);
+        if uri == XML_NAMESPACE {
+            return Ok(Cow::Owned(format!("xml:{}", local_name)));
+        }
+
+        match self.namespaces.iter().find(|(_, u)| u == uri) {
+            Some((prefix, _)) if prefix.is_empty() => {
+                if attribute {
+                    Err(Reason::Message(format!(
+                        "attribute `{}` cannot use the default namespace",
+                        name
+                    )))
+                } else {
+                    Ok(Cow::Owned(local_name.to_string()))
+                }
+            }
+            Some((prefix, _)) => Ok(Cow::Owned(format!("{}:{}", prefix, local_name))),
+            None => Err(Reason::Message(format!(
+                "no prefix registered for namespace `{}`",
+                uri
+            ))),
+        }
+    }
+
+    /// Adds `xmlns` attributes for all registered namespaces
+    fn push_declarations(&self, start: &mut BytesStart) {
+        for (prefix, uri) in &self.namespaces {
+            if prefix.is_empty() {
+                start.push_attribute(("xmlns", uri as &str));
+            } else {
+                start.push_attribute((&format!("xmlns:{}", prefix) as &str, uri as &str));
+            }
+        }
+    }
+}
+
 /// A Serializer
 pub struct Serializer<'r, 'a, W: Write> {
     /// Writer
@@ -65,8 +153,40 @@ pub struct Serializer<'r, 'a, W: Write> {
     ///
     /// Fragments do not have a XML Declaration
     pub(crate) fragment: bool,
+
+    /// Settings shared with the serializers of nested elements
+    pub(crate) settings: Rc<Settings>,
+
+    /// Next element written is a root element, which gets the namespace declarations
+    pub(crate) top_level: bool,
+
+    /// Nesting level of the next element written, used for pretty-printing
+    pub(crate) depth: usize,
+
+    /// Output is written to the document, so it is encoded with the document encoding
+    ///
+    /// Nested serializers write UTF-8 to buffers.
+    pub(crate) encode_output: bool,
+
+    /// Attribute added to the start tag of `root_tag`
+    pub(crate) root_attribute: Option<(&'r str, &'r str)>,
+
+    /// Set when text is written without element, so the parent element has text content
+    pub(crate) has_text: Option<&'r Cell<bool>>,
+
+    /// The next map is written as entries, see [`entries`](crate::entries)
+    pub(crate) map_entries: bool,
+
+    /// A sequence is written as content of one element `root_tag` of a newtype variant
+    pub(crate) variant_content: bool,
 }
 
+/// Precedes indentation in the buffers of nested serializers
+///
+/// Elements with text content remove the indentation of their children, see
+/// [`Struct`]. The byte is never part of UTF-8.
+pub(crate) const INDENT_MARK: u8 = 0xFF;
+
 impl<'r, 'a, W: Write> Serializer<'r, 'a, W> {
     /// Creates a new `Serializer` that uses struct name as a root tag name.
     ///
@@ -130,76 +250,550 @@ impl<'r, 'a, W: Write> Serializer<'r, 'a, W> {
             root_tag,
             version: XmlVersion::v1_0,
             fragment: false,
+            settings: Rc::new(Settings::default()),
+            top_level: true,
+            depth: 0,
+            encode_output: true,
+            root_attribute: None,
+            has_text: None,
+            map_entries: false,
+            variant_content: false,
         }
     }
 
+    /// Write the XML declaration before the root element, enabled by default
     pub fn with_xmldecl(self, write_xmldecl: bool) -> Self {
         Self {
-            writer: self.writer,
-            root_tag: self.root_tag,
-            version: self.version,
-            fragment: write_xmldecl,
+            fragment: !write_xmldecl,
+            ..self
         }
     }
 
     pub fn with_version(self, version: XmlVersion) -> Self {
-        Self {
-            writer: self.writer,
-            root_tag: self.root_tag,
-            version,
-            fragment: self.fragment,
+        Self { version, ..self }
+    }
+
+    /// Registers a prefix for a namespace
+    ///
+    /// The namespace is declared on the root element. Element and attribute names in the form
+    /// `{uri}local` are written as `prefix:local`, or as `local` for an empty prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// #[serde(rename = "{http://www.w3.org/2003/05/soap-envelope}Envelope")]
+    /// struct Envelope {
+    ///     #[serde(rename = "{http://www.w3.org/2003/05/soap-envelope}Body")]
+    ///     body: String,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer)
+    ///     .with_xmldecl(false)
+    ///     .with_namespace("soap", "http://www.w3.org/2003/05/soap-envelope");
+    ///
+    /// Envelope { body: "content".into() }.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Body>content</soap:Body></soap:Envelope>"#
+    /// );
+    /// ```
+    pub fn with_namespace(mut self, prefix: &str, uri: &str) -> Self {
+        let namespaces = &mut Rc::make_mut(&mut self.settings).namespaces;
+        namespaces.retain(|(p, _)| p != prefix);
+        namespaces.push((prefix.to_string(), uri.to_string()));
+        self
+    }
+
+    /// Pretty-print with `indent_size` times `indent_char` per nesting level
+    ///
+    /// Every element starts on a new line. Text content is written unchanged, so the content of
+    /// elements with text, like mixed content, is not indented. Use a writer without indentation
+    /// in this mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Point {
+    ///     #[serde(rename = "@lat")]
+    ///     lat: f64,
+    ///     ele: f64,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Track {
+    ///     name: String,
+    ///     #[serde(rename = "trkpt")]
+    ///     points: Vec<Point>,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer).with_indent(b' ', 2);
+    ///
+    /// let track = Track {
+    ///     name: "Morning run".into(),
+    ///     points: vec![Point { lat: 47.5, ele: 402.0 }],
+    /// };
+    /// track.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <Track>
+    ///   <name>Morning run</name>
+    ///   <trkpt lat="47.5">
+    ///     <ele>402</ele>
+    ///   </trkpt>
+    /// </Track>"#
+    /// );
+    /// ```
+    pub fn with_indent(mut self, indent_char: u8, indent_size: usize) -> Self {
+        Rc::make_mut(&mut self.settings).indent = Some((indent_char, indent_size));
+        self
+    }
+
+    /// Character encoding of the output, UTF-8 by default
+    ///
+    /// Characters of text and attribute values without representation in the encoding are
+    /// written as character references. UTF-16 documents start with a byte order mark.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{Encoding, Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Payment {
+    ///     #[serde(rename = "@currency")]
+    ///     currency: &'static str,
+    ///     payee: &'static str,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer)
+    ///     .with_encoding(Encoding::Iso8859_1)
+    ///     .with_standalone(true);
+    ///
+    /// Payment { currency: "€", payee: "Jürgen" }.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     buffer,
+    ///     &b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\" standalone=\"yes\"?>\
+    ///         <Payment currency=\"&#x20AC;\"><payee>J\xFCrgen</payee></Payment>"[..]
+    /// );
+    /// ```
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        Rc::make_mut(&mut self.settings).encoding = encoding;
+        self
+    }
+
+    /// Value of `standalone` in the XML declaration, not written by default
+    pub fn with_standalone(mut self, standalone: bool) -> Self {
+        Rc::make_mut(&mut self.settings).standalone = Some(standalone);
+        self
+    }
+
+    /// Encoding for bytes, base64 by default
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        Rc::make_mut(&mut self.settings).binary_encoding = encoding;
+        self
+    }
+
+    /// Handling of characters, which are not allowed in XML, in text and attribute values
+    ///
+    /// They are written unchanged by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{ErrorKind, InvalidChars, Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Comment {
+    ///     author: &'static str,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer).with_invalid_chars(InvalidChars::Error);
+    /// let err = Comment { author: "\u{0}" }.serialize(&mut ser).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::InvalidChar);
+    /// assert_eq!(err.path(), Some("author"));
+    /// ```
+    pub fn with_invalid_chars(mut self, invalid_chars: InvalidChars) -> Self {
+        Rc::make_mut(&mut self.settings).invalid_chars = invalid_chars;
+        self
+    }
+
+    /// Handling of map keys, which are no valid XML names
+    ///
+    /// They are written unchanged by default. Keys starting with `$` are not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use xserde::{InvalidNames, Serializer, Writer};
+    /// use serde::Serialize;
+    ///
+    /// let mut metadata = BTreeMap::new();
+    /// metadata.insert("1st item", "a");
+    /// metadata.insert("title", "b");
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new_with_root(&mut writer, Some("metadata"))
+    ///     .with_xmldecl(false)
+    ///     .with_invalid_names(InvalidNames::Entry);
+    /// metadata.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<metadata><entry key="1st item">a</entry><title>b</title></metadata>"#
+    /// );
+    /// ```
+    pub fn with_invalid_names(mut self, invalid_names: InvalidNames) -> Self {
+        Rc::make_mut(&mut self.settings).invalid_names = invalid_names;
+        self
+    }
+
+    /// Literals for booleans, `true` and `false` by default
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{Booleans, Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Account {
+    ///     #[serde(rename = "@active")]
+    ///     active: bool,
+    ///     locked: bool,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer)
+    ///     .with_xmldecl(false)
+    ///     .with_booleans(Booleans::custom("Y", "N"));
+    /// let account = Account { active: true, locked: false };
+    /// account.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<Account active="Y"><locked>N</locked></Account>"#
+    /// );
+    /// ```
+    pub fn with_booleans(mut self, booleans: Booleans) -> Self {
+        Rc::make_mut(&mut self.settings).booleans = booleans;
+        self
+    }
+
+    /// Writes map entries as elements `element` with the key in the attribute `key`, instead of
+    /// `<entry key="k">value</entry>`
+    ///
+    /// These names are used for fields with [`entries`](crate::entries) and for
+    /// [`InvalidNames::Entry`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use xserde::{Serializer, Writer};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Prices {
+    ///     #[serde(with = "xserde::entries")]
+    ///     items: BTreeMap<&'static str, u32>,
+    /// }
+    ///
+    /// let mut items = BTreeMap::new();
+    /// items.insert("apple", 2);
+    /// items.insert("pear", 3);
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new_with_root(&mut writer, Some("prices"))
+    ///     .with_xmldecl(false)
+    ///     .with_entry_names("item", "name");
+    /// Prices { items }.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<prices><items><item name="apple">2</item><item name="pear">3</item></items></prices>"#
+    /// );
+    /// ```
+    pub fn with_entry_names(mut self, element: &str, key: &str) -> Self {
+        Rc::make_mut(&mut self.settings).entry_names = Some((element.to_string(), key.to_string()));
+        self
+    }
+
+    /// Writes the processing instructions and the document type declaration of `prolog`
+    /// after the XML declaration
+    ///
+    /// Version and `standalone` are taken from `prolog` if it has a declaration. The encoding
+    /// is set with [`with_encoding`](#method.with_encoding). Fragments have no prolog.
+    pub fn with_prolog(mut self, prolog: &Prolog) -> Self {
+        if let Some(version) = prolog.version() {
+            self.version = version;
+        }
+        let settings = Rc::make_mut(&mut self.settings);
+        if let Some(standalone) = prolog.standalone() {
+            settings.standalone = Some(standalone);
+        }
+        settings.doctype = prolog.doctype().cloned();
+        settings.instructions = prolog.instructions().to_vec();
+        self
+    }
+
+    /// Writes the root element `name` incrementally
+    ///
+    /// Children are written directly to the writer one at a time, so the element does not need
+    /// to be held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use xserde::{Serializer, Writer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row {
+    ///     #[serde(rename = "@id")]
+    ///     id: u32,
+    /// }
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut writer = Writer::new(&mut buffer);
+    /// let mut ser = Serializer::new(&mut writer).with_xmldecl(false);
+    ///
+    /// let mut table = ser.stream_element("table")?.with_attribute("name", "users")?;
+    /// for id in 1..=2 {
+    ///     table.push_element("row", &Row { id })?;
+    /// }
+    /// table.end()?;
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     r#"<table name="users"><row id="1"/><row id="2"/></table>"#
+    /// );
+    /// # Ok::<(), xserde::Error>(())
+    /// ```
+    pub fn stream_element<'s>(
+        &'s mut self,
+        name: &'s str,
+    ) -> Result<ElementStream<'s, 'r, 'a, W>, Error> {
+        ElementStream::new(self, name)
+    }
+
+    /// Creates a serializer for nested content with the same settings
+    pub(crate) fn nested<'n, V: Write>(
+        &self,
+        writer: &'n mut Writer<V>,
+        root_tag: Option<&'n str>,
+    ) -> Serializer<'n, 'n, V> {
+        Serializer {
+            writer,
+            root_tag,
+            version: self.version,
+            fragment: true,
+            settings: self.settings.clone(),
+            top_level: false,
+            depth: self.depth + 1,
+            encode_output: false,
+            root_attribute: None,
+            has_text: None,
+            map_entries: false,
+            variant_content: false,
         }
     }
 
+    pub(crate) fn resolve_name<'n>(
+        &self,
+        name: &'n str,
+        attribute: bool,
+    ) -> Result<Cow<'n, str>, Error> {
+        self.settings
+            .resolve_name(name, attribute)
+            .map_err(|reason| self.error(reason))
+    }
+
+    /// Creates start tag, which contains the namespace declarations for a root element
+    pub(crate) fn start_tag<'n>(&mut self, name: &'n str) -> Result<BytesStart<'n>, Error> {
+        let mut start = match self.resolve_name(name, false)? {
+            Cow::Borrowed(name) => BytesStart::borrowed_name(name.as_bytes()),
+            Cow::Owned(name) => BytesStart::owned_name(name),
+        };
+        if std::mem::replace(&mut self.top_level, false) {
+            self.settings.push_declarations(&mut start);
+        }
+        if let Some((key, value)) = self.root_attribute {
+            if Some(name) == self.root_tag {
+                start.push_attribute(self.attribute(key, value)?);
+            }
+        }
+        Ok(start)
+    }
+
+    /// Writes the XML declaration, unless a fragment is written
+    pub(crate) fn write_declaration(&mut self) -> Result<(), Error> {
+        if !self.fragment {
+            let encoding = self.settings.encoding;
+            let standalone =
+                self.settings
+                    .standalone
+                    .map(|standalone| if standalone { &b"yes"[..] } else { b"no" });
+            self.writer.write(encoding.bom())?;
+            self.write_event(Event::Decl(BytesDecl::new(
+                self.version.as_bytes(),
+                Some(encoding.name().as_bytes()),
+                standalone,
+            )))?;
+            let settings = self.settings.clone();
+            let pretty = settings.indent.is_some();
+            if pretty {
+                self.write_raw(b"\n")?;
+            }
+            for instruction in &settings.instructions {
+                let instruction = instruction.to_string();
+                self.write_event(Event::PI(BytesText::from_escaped_str(&instruction)))?;
+                if pretty {
+                    self.write_raw(b"\n")?;
+                }
+            }
+            if let Some(doctype) = &settings.doctype {
+                let doctype = doctype.to_string();
+                self.write_event(Event::DocType(BytesText::from_escaped_str(&doctype)))?;
+                if pretty {
+                    self.write_raw(b"\n")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `event` in the output encoding
+    pub(crate) fn write_event(&mut self, event: Event) -> Result<(), Error> {
+        if !self.encode_output || self.settings.encoding == Encoding::Utf8 {
+            return Ok(self.writer.write_event(event)?);
+        }
+        let mut buffer = Vec::new();
+        Writer::new(&mut buffer).write_event(event)?;
+        self.write_raw(&buffer)
+    }
+
+    /// Writes serialized XML in the output encoding
+    pub(crate) fn write_raw(&mut self, xml: &[u8]) -> Result<(), Error> {
+        let encoding = self.settings.encoding;
+        if !self.encode_output || encoding == Encoding::Utf8 {
+            return Ok(self.writer.write(xml)?);
+        }
+        let xml = std::str::from_utf8(xml).map_err(|err| self.error(Reason::Xml(err.into())))?;
+        let encoded = encoding
+            .encode(xml)
+            .map_err(|c| self.error(Reason::Unencodable(c, encoding)))?;
+        Ok(self.writer.write(&encoded)?)
+    }
+
+    /// Starts a new line for a tag at the current depth, when pretty-printing
+    ///
+    /// Start tags of root elements are not preceded by a line break. Nested serializers mark
+    /// the indentation with [`INDENT_MARK`], so that it can be removed from mixed content.
+    pub(crate) fn write_indent(&mut self, end_tag: bool) -> Result<(), Error> {
+        if let Some((indent_char, indent_size)) = self.settings.indent {
+            if self.depth > 0 || end_tag {
+                let mut line = Vec::with_capacity(2 + indent_size * self.depth);
+                if !self.encode_output {
+                    line.push(INDENT_MARK);
+                }
+                line.push(b'\n');
+                line.resize(line.len() + indent_size * self.depth, indent_char);
+                self.write_raw(&line)?;
+            }
+        }
+        Ok(())
+    }
+
     fn write_primitive<P: std::fmt::Display>(
         &mut self,
         value: P,
         escaped: bool,
     ) -> Result<(), Error> {
-        let value = value.to_string().into_bytes();
+        let value = value.to_string();
         let event = if escaped {
-            BytesText::from_escaped(value)
+            BytesText::from_escaped_str(value)
         } else {
-            BytesText::from_plain(&value)
+            BytesText::from_escaped_str(self.escape(&value)?.into_owned())
         };
 
-        self.render_tag_around(|writer| Ok(writer.write_event(Event::Text(event))?))
+        if let Some(root) = self.root_tag {
+            let start = self.start_tag(root)?;
+            self.write_indent(false)?;
+            self.write_event(Event::Start(start))?;
+            self.write_event(Event::Text(event))?;
+            self.write_end(root)
+        } else {
+            if let Some(has_text) = self.has_text {
+                has_text.set(has_text.get() || !event.is_empty());
+            }
+            self.write_event(Event::Text(event))
+        }
+    }
+
+    /// Attribute with an escaped value
+    pub(crate) fn attribute<'k>(&self, key: &'k str, value: &str) -> Result<Attribute<'k>, Error> {
+        Ok(Attribute {
+            key: key.as_bytes(),
+            value: Cow::Owned(self.escape(value)?.into_owned().into_bytes()),
+        })
     }
 
-    /// Writes self-closed tag `<tag_name/>` into inner writer
-    fn write_self_closed(&mut self, tag_name: &str) -> Result<(), Error> {
-        self.writer
-            .write_event(Event::Empty(BytesStart::borrowed_name(tag_name.as_bytes())))?;
-        Ok(())
+    /// Escapes text or an attribute value
+    fn escape<'t>(&self, text: &'t str) -> Result<Cow<'t, str>, Error> {
+        chars::escape(text, self.version, self.settings.invalid_chars)
+            .map_err(|c| self.error(Reason::InvalidChar(c)))
     }
 
-    fn render_tag_around(
-        &mut self,
-        f: impl FnOnce(&mut Writer<W>) -> Result<(), Error>,
-    ) -> Result<(), Error> {
-        if let Some(root) = self.root_tag {
-            self.write_tag_start(root)?;
-            f(self.writer)?;
-            self.write_tag_end(root)
-        } else {
-            f(self.writer)
-        }
+    /// Writes self-closed tag `<tag_name/>` into inner writer
+    fn write_self_closed(&mut self, tag_name: &str) -> Result<(), Error> {
+        let start = self.start_tag(tag_name)?;
+        self.write_indent(false)?;
+        self.write_event(Event::Empty(start))
     }
 
+    /// Writes the start tag of an element, which contains elements
     fn write_tag_start(&mut self, tag: &str) -> Result<(), Error> {
-        Ok(self
-            .writer
-            .write_event(Event::Start(BytesStart::borrowed_name(tag.as_bytes())))?)
+        let start = self.start_tag(tag)?;
+        self.write_indent(false)?;
+        self.write_event(Event::Start(start))?;
+        self.depth += 1;
+        Ok(())
     }
 
+    /// Writes the end tag of an element started with `write_tag_start`
     fn write_tag_end(&mut self, tag: &str) -> Result<(), Error> {
-        Ok(self
-            .writer
-            .write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))?)
+        self.depth -= 1;
+        self.write_indent(true)?;
+        self.write_end(tag)
+    }
+
+    fn write_end(&mut self, tag: &str) -> Result<(), Error> {
+        let tag = self.resolve_name(tag, false)?;
+        self.write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))
     }
 
+    /// Serialization errors have no location, their path is set by `Error::within`
     fn error(&self, reason: Reason) -> Error {
-        // TODO: set offset
         Error::new(reason, 0)
     }
 }
@@ -217,7 +811,8 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
     type SerializeStructVariant = Struct<'r, 'w, 'a, W>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
-        self.write_primitive(if v { "true" } else { "false" }, true)
+        let literal = self.settings.booleans.literal(v).to_string();
+        self.write_primitive(literal, false)
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
@@ -252,14 +847,12 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
         self.write_primitive(v, true)
     }
 
-    serde_if_integer128! {
-        fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> {
-            self.write_primitive(v, true)
-        }
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> {
+        self.write_primitive(v, true)
+    }
 
-        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> {
-            self.write_primitive(v, true)
-        }
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> {
+        self.write_primitive(v, true)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
@@ -278,10 +871,9 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
         self.write_primitive(value, false)
     }
 
-    fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Error> {
-        // TODO: I imagine you'd want to use base64 here.
-        // Not sure how to roundtrip effectively though...
-        Err(self.error(Reason::Unsupported("serialize_bytes")))
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Error> {
+        let value = self.settings.binary_encoding.encode(value);
+        self.write_primitive(value, true)
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Error> {
@@ -306,17 +898,23 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Error> {
-        self.render_tag_around(|writer| {
-            writer.write_event(Event::Empty(BytesStart::borrowed_name(variant.as_bytes())))?;
-            Ok(())
-        })
+        if let Some(root) = self.root_tag {
+            self.write_tag_start(root)?;
+            self.write_self_closed(variant)?;
+            self.write_tag_end(root)
+        } else {
+            self.write_self_closed(variant)
+        }
     }
 
     fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Error> {
+        if name == MAP_ENTRIES {
+            self.map_entries = true;
+        }
         value.serialize(self)
     }
 
@@ -327,13 +925,45 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Error> {
-        self.render_tag_around(|writer| {
-            let mut serializer = Serializer::new_with_root(writer, Some(variant));
-            value.serialize(&mut serializer)
-        })
+        if let Some(root) = self.root_tag {
+            self.write_tag_start(root)?;
+        }
+
+        // text in mixed content is written without element
+        let variant = if variant == TEXT {
+            None
+        } else {
+            Some(self.resolve_name(variant, false)?)
+        };
+        let mut serializer = Serializer {
+            writer: &mut *self.writer,
+            root_tag: variant.as_deref(),
+            version: self.version,
+            fragment: true,
+            settings: self.settings.clone(),
+            top_level: std::mem::replace(&mut self.top_level, false),
+            depth: self.depth,
+            encode_output: self.encode_output,
+            root_attribute: None,
+            has_text: self.has_text,
+            map_entries: false,
+            variant_content: variant.is_some(),
+        };
+        value.serialize(&mut serializer)?;
+
+        if let Some(root) = self.root_tag {
+            self.write_tag_end(root)?;
+        }
+        Ok(())
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        if std::mem::take(&mut self.variant_content) {
+            if let Some(root) = self.root_tag.take() {
+                self.write_tag_start(root)?;
+                return Ok(Seq::with_end(self, root));
+            }
+        }
         Ok(Seq::new(self))
     }
 
@@ -364,7 +994,8 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
-        Ok(Struct::new(self, self.root_tag.unwrap()))
+        let entries = std::mem::take(&mut self.map_entries);
+        Struct::new_map(self, self.root_tag.unwrap_or(""), entries)
     }
 
     fn serialize_struct(
@@ -372,7 +1003,7 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
         name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Error> {
-        Ok(Struct::new(self, self.root_tag.unwrap_or(name)))
+        Struct::new(self, self.root_tag.unwrap_or(name))
     }
 
     fn serialize_struct_variant(
@@ -386,7 +1017,7 @@ impl<'r, 'a, 'w, W: Write> ser::Serializer for &'w mut Serializer<'r, 'a, W> {
             self.write_tag_start(root)?;
         }
 
-        Ok(Struct::new(self, variant))
+        Struct::new(self, variant)
     }
 }
 
@@ -1010,9 +1641,10 @@ mod tests {
                     string: "answer".to_string(),
                 };
 
+                // serde 1.0.181 and later serialize the adjacent tag as a unit variant
                 assert_eq!(
                     to_string_with_root(&node, "root").unwrap(),
-                    r#"<root><tag>flatten</tag><content><float>42</float><string>answer</string></content></root>"#
+                    r#"<root><tag><flatten/></tag><content><float>42</float><string>answer</string></content></root>"#
                 );
             }
         }
@@ -1213,5 +1845,985 @@ mod tests {
                 r#"<root><xsi:element>string</xsi:element></root>"#
             );
         }
+
+        const SOAP: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+        #[derive(Serialize)]
+        #[serde(rename = "{http://www.w3.org/2003/05/soap-envelope}Envelope")]
+        struct Envelope {
+            #[serde(rename = "{http://www.w3.org/2003/05/soap-envelope}Body")]
+            body: Body,
+        }
+
+        #[derive(Serialize)]
+        struct Body {
+            #[serde(rename = "@{http://www.w3.org/2003/05/soap-envelope}encodingStyle")]
+            encoding_style: String,
+            #[serde(rename = "@{http://www.w3.org/XML/1998/namespace}lang")]
+            lang: String,
+            #[serde(rename = "{urn:example}item")]
+            item: Vec<i32>,
+        }
+
+        fn envelope() -> Envelope {
+            Envelope {
+                body: Body {
+                    encoding_style: "style".to_string(),
+                    lang: "en".to_string(),
+                    item: vec![1, 2],
+                },
+            }
+        }
+
+        fn to_string_with_namespaces<S: Serialize>(
+            value: &S,
+            namespaces: &[(&str, &str)],
+        ) -> Result<String, Error> {
+            let mut buffer = Vec::new();
+            let mut xml_writer = Writer::new(&mut buffer);
+            let mut serializer = Serializer::new(&mut xml_writer).with_xmldecl(false);
+            for (prefix, uri) in namespaces {
+                serializer = serializer.with_namespace(prefix, uri);
+            }
+            value.serialize(&mut serializer)?;
+            Ok(String::from_utf8(buffer).unwrap())
+        }
+
+        #[test]
+        fn declared_prefixes() {
+            assert_eq!(
+                to_string_with_namespaces(&envelope(), &[("soap", SOAP), ("ex", "urn:example")])
+                    .unwrap(),
+                r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:ex="urn:example"><soap:Body soap:encodingStyle="style" xml:lang="en"><ex:item>1</ex:item><ex:item>2</ex:item></soap:Body></soap:Envelope>"#
+            );
+        }
+
+        #[test]
+        fn default_namespace() {
+            assert_eq!(
+                to_string_with_namespaces(&envelope(), &[("", SOAP), ("ex", "urn:example")])
+                    .unwrap_err()
+                    .to_string(),
+                "attribute `{http://www.w3.org/2003/05/soap-envelope}encodingStyle` cannot use the default namespace"
+            );
+
+            #[derive(Serialize)]
+            #[serde(rename = "{urn:example}root")]
+            struct Root {
+                #[serde(rename = "{urn:example}child")]
+                child: i32,
+            }
+
+            assert_eq!(
+                to_string_with_namespaces(&Root { child: 1 }, &[("", "urn:example")]).unwrap(),
+                r#"<root xmlns="urn:example"><child>1</child></root>"#
+            );
+        }
+
+        #[test]
+        fn unregistered_namespace() {
+            assert_eq!(
+                to_string_with_namespaces(&envelope(), &[("soap", SOAP)])
+                    .unwrap_err()
+                    .to_string(),
+                "no prefix registered for namespace `urn:example`"
+            );
+        }
+
+        #[test]
+        fn roundtrip() {
+            #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+            #[serde(rename = "{urn:example}root")]
+            struct Root {
+                #[serde(rename = "@{urn:example}attr")]
+                attr: i32,
+                #[serde(rename = "{urn:example}child")]
+                child: i32,
+            }
+
+            let root = Root { attr: 1, child: 2 };
+            let xml = to_string_with_namespaces(&root, &[("a", "urn:example")]).unwrap();
+            let options = crate::DeserializerOptions::new().resolve_namespaces(true);
+            assert_eq!(options.from_reader(xml.as_bytes()).ok(), Some(root));
+        }
+    }
+
+    mod stream {
+        use super::*;
+
+        #[derive(Serialize)]
+        struct Row {
+            #[serde(rename = "@id")]
+            id: u32,
+            name: &'static str,
+        }
+
+        #[test]
+        fn document() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_namespace("t", "urn:table");
+
+            let mut table = ser
+                .stream_element("{urn:table}table")
+                .unwrap()
+                .with_attribute("{urn:table}name", "rows")
+                .unwrap()
+                .with_attribute("empty", &None::<u32>)
+                .unwrap();
+            table.push(&Row { id: 1, name: "one" }).unwrap();
+            table.push_element("{urn:table}count", &2).unwrap();
+            table.end().unwrap();
+
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<t:table xmlns:t="urn:table" t:name="rows">"#,
+                    r#"<Row id="1"><name>one</name></Row>"#,
+                    r#"<t:count>2</t:count>"#,
+                    r#"</t:table>"#
+                )
+            );
+        }
+
+        #[test]
+        fn empty() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_xmldecl(false);
+
+            ser.stream_element("table").unwrap().end().unwrap();
+            assert_eq!(String::from_utf8(buffer).unwrap(), "<table/>");
+        }
+
+        #[test]
+        fn attribute_after_child() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer);
+
+            let mut table = ser.stream_element("table").unwrap();
+            table.push_element("row", "1").unwrap();
+            assert!(table.with_attribute("name", "rows").is_err());
+        }
+    }
+
+    mod binary {
+        use serde::Deserialize;
+        use serde_bytes::ByteBuf;
+
+        use super::*;
+        use crate::de::from_str;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Blob {
+            #[serde(rename = "@hash", with = "serde_bytes")]
+            hash: Vec<u8>,
+            data: ByteBuf,
+        }
+
+        fn blob() -> Blob {
+            Blob {
+                hash: vec![0xde, 0xad, 0xbe, 0xef],
+                data: ByteBuf::from(b"hello\0world".to_vec()),
+            }
+        }
+
+        #[test]
+        fn base64() {
+            let xml = to_string_with_root(&blob(), "Blob").unwrap();
+            assert_eq!(
+                xml,
+                r#"<Blob hash="3q2+7w=="><data>aGVsbG8Ad29ybGQ=</data></Blob>"#
+            );
+            assert_eq!(from_str::<Blob>(&xml).unwrap(), blob());
+        }
+
+        #[test]
+        fn hex() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(false)
+                .with_binary_encoding(BinaryEncoding::Hex);
+            blob().serialize(&mut ser).unwrap();
+            let xml = String::from_utf8(buffer).unwrap();
+            assert_eq!(
+                xml,
+                r#"<Blob hash="deadbeef"><data>68656c6c6f00776f726c64</data></Blob>"#
+            );
+
+            let options =
+                crate::DeserializerOptions::new().with_binary_encoding(BinaryEncoding::Hex);
+            assert_eq!(options.from_str::<Blob>(&xml).unwrap(), blob());
+        }
+
+        #[test]
+        fn invalid() {
+            let xml = r#"<Blob hash="3q2+7w=="><data>a!</data></Blob>"#;
+            let err = from_str::<Blob>(xml).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid base64 value 'a!'"));
+
+            let xml = r#"<Blob hash="3q2+7w"><data>aGVsbG8Ad29ybGQ=</data></Blob>"#;
+            let err = from_str::<Blob>(xml).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid base64 value '3q2+7w'"));
+        }
+    }
+
+    mod pretty {
+        use super::*;
+
+        #[derive(Serialize)]
+        struct Name {
+            #[serde(rename = "@lang")]
+            lang: &'static str,
+            #[serde(rename = "$value")]
+            text: &'static str,
+        }
+
+        #[derive(Serialize)]
+        enum Shape {
+            Circle { radius: u32 },
+            Dot,
+        }
+
+        #[derive(Serialize)]
+        struct Drawing {
+            name: Name,
+            #[serde(rename = "shape")]
+            shapes: Vec<Shape>,
+            empty: (),
+        }
+
+        fn to_pretty_string<S: Serialize>(value: &S, xmldecl: bool) -> String {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(xmldecl)
+                .with_indent(b'\t', 1);
+            value.serialize(&mut ser).unwrap();
+            String::from_utf8(buffer).unwrap()
+        }
+
+        #[test]
+        fn nested() {
+            let drawing = Drawing {
+                name: Name {
+                    lang: "en",
+                    text: "  two\nlines ",
+                },
+                shapes: vec![Shape::Circle { radius: 2 }, Shape::Dot],
+                empty: (),
+            };
+            assert_eq!(
+                to_pretty_string(&drawing, true),
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                    "<Drawing>\n",
+                    "\t<name lang=\"en\">  two\nlines </name>\n",
+                    "\t<shape>\n",
+                    "\t\t<Circle>\n",
+                    "\t\t\t<radius>2</radius>\n",
+                    "\t\t</Circle>\n",
+                    "\t</shape>\n",
+                    "\t<shape>\n",
+                    "\t\t<Dot/>\n",
+                    "\t</shape>\n",
+                    "</Drawing>",
+                )
+            );
+        }
+
+        #[test]
+        fn stream() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(false)
+                .with_indent(b' ', 2);
+
+            let mut table = ser.stream_element("table").unwrap();
+            table
+                .push_element(
+                    "row",
+                    &Name {
+                        lang: "de",
+                        text: "eins",
+                    },
+                )
+                .unwrap();
+            table.push_element("count", &1).unwrap();
+            table.end().unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                "<table>\n  <row lang=\"de\">eins</row>\n  <count>1</count>\n</table>"
+            );
+        }
+
+        #[test]
+        fn mixed_content() {
+            use crate::element::Element;
+
+            let xml =
+                "<doc><p>Hello <b>world</b>!</p><p>a <b><i>x</i></b></p><list><li/></list></doc>";
+            let options = crate::DeserializerOptions::new().preserve_whitespace(true);
+            let element: Element = options.from_str(xml).unwrap();
+            let pretty = to_pretty_string(&element, false);
+            assert_eq!(
+                pretty,
+                concat!(
+                    "<doc>\n",
+                    "\t<p>Hello <b>world</b>!</p>\n",
+                    "\t<p>a <b><i>x</i></b></p>\n",
+                    "\t<list>\n",
+                    "\t\t<li/>\n",
+                    "\t</list>\n",
+                    "</doc>",
+                )
+            );
+            // only the whitespace between elements without text is added
+            let reparsed: Element = options.from_str(&pretty).unwrap();
+            for (p, expected) in reparsed.children().iter().zip(&element.children()[..2]) {
+                assert_eq!(p.text(), expected.text());
+                assert_eq!(p.children(), expected.children());
+            }
+        }
+
+        #[test]
+        fn text_variant() {
+            #[derive(Serialize)]
+            #[serde(rename_all = "lowercase")]
+            enum Inline {
+                #[serde(rename = "$text")]
+                Text(&'static str),
+                B(&'static str),
+            }
+
+            #[derive(Serialize)]
+            struct P {
+                #[serde(rename = "$value")]
+                content: Vec<Inline>,
+            }
+
+            #[derive(Serialize)]
+            struct Doc {
+                p: P,
+            }
+
+            let doc = Doc {
+                p: P {
+                    content: vec![
+                        Inline::Text("Hello "),
+                        Inline::B("world"),
+                        Inline::Text("!"),
+                    ],
+                },
+            };
+            assert_eq!(
+                to_pretty_string(&doc, false),
+                "<Doc>\n\t<p>Hello <b>world</b>!</p>\n</Doc>"
+            );
+        }
+    }
+
+    mod encoding {
+        use std::collections::BTreeMap;
+
+        use super::*;
+
+        #[derive(Serialize)]
+        struct Note {
+            #[serde(rename = "@from")]
+            from: &'static str,
+            #[serde(rename = "$value")]
+            text: &'static str,
+        }
+
+        fn to_bytes<S: Serialize>(value: &S, encoding: Encoding) -> Result<Vec<u8>, Error> {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_encoding(encoding);
+            value.serialize(&mut ser)?;
+            Ok(buffer)
+        }
+
+        #[test]
+        fn windows_1252() {
+            let note = Note {
+                from: "Zoë",
+                text: "5 € – ok",
+            };
+            assert_eq!(
+                to_bytes(&note, Encoding::Windows1252).unwrap(),
+                &b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\
+                    <Note from=\"Zo\xEB\">5 \x80 \x96 ok</Note>"[..]
+            );
+        }
+
+        #[test]
+        fn utf16() {
+            let note = Note {
+                from: "a",
+                text: "€",
+            };
+            let xml = r#"<?xml version="1.0" encoding="UTF-16"?><Note from="a">€</Note>"#;
+            let mut expected = b"\xFE\xFF".to_vec();
+            for unit in xml.encode_utf16() {
+                expected.extend_from_slice(&unit.to_be_bytes());
+            }
+            assert_eq!(to_bytes(&note, Encoding::Utf16Be).unwrap(), expected);
+        }
+
+        #[test]
+        fn unencodable_name() {
+            let mut map = BTreeMap::new();
+            map.insert("ő", 1);
+            // references in text and attribute values
+            let note = Note {
+                from: "ő",
+                text: "ő",
+            };
+            assert!(to_bytes(&note, Encoding::Iso8859_1).is_ok());
+
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new_with_root(&mut writer, Some("root"))
+                .with_encoding(Encoding::Iso8859_1);
+            let err = map.serialize(&mut ser).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::InvalidValue);
+        }
+
+        #[test]
+        fn standalone() {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_standalone(false);
+            Note {
+                from: "a",
+                text: "b",
+            }
+            .serialize(&mut ser)
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?><Note from="a">b</Note>"#
+            );
+        }
+    }
+
+    mod prolog {
+        use super::*;
+
+        #[derive(Serialize)]
+        #[serde(rename = "html")]
+        struct Html {
+            body: &'static str,
+        }
+
+        #[test]
+        fn pretty() {
+            let prolog = Prolog::new()
+                .with_standalone(false)
+                .with_instruction(ProcessingInstruction::new(
+                    "xml-stylesheet",
+                    "href=\"style.css\"",
+                ))
+                .with_doctype(DocType::new("html").with_system_id("about:legacy-compat"));
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_prolog(&prolog)
+                .with_indent(b' ', 2);
+            Html { body: "text" }.serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?xml-stylesheet href="style.css"?>
+<!DOCTYPE html SYSTEM "about:legacy-compat">
+<html>
+  <body>text</body>
+</html>"#
+            );
+        }
+
+        #[test]
+        fn fragment() {
+            let prolog = Prolog::new().with_doctype(DocType::new("html"));
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(false)
+                .with_prolog(&prolog);
+            Html { body: "text" }.serialize(&mut ser).unwrap();
+            assert_eq!(buffer, b"<html><body>text</body></html>");
+        }
+
+        #[test]
+        fn xmldecl() {
+            let to_string = |write_xmldecl: bool| {
+                let mut buffer = Vec::new();
+                let mut writer = Writer::new(&mut buffer);
+                let mut ser = Serializer::new(&mut writer).with_xmldecl(write_xmldecl);
+                Html { body: "text" }.serialize(&mut ser).unwrap();
+                String::from_utf8(buffer).unwrap()
+            };
+            assert_eq!(
+                to_string(true),
+                r#"<?xml version="1.0" encoding="UTF-8"?><html><body>text</body></html>"#
+            );
+            assert_eq!(to_string(false), "<html><body>text</body></html>");
+        }
+    }
+
+    mod version {
+        use super::*;
+
+        #[derive(Serialize)]
+        struct Note {
+            #[serde(rename = "@title")]
+            title: &'static str,
+            text: &'static str,
+        }
+
+        #[test]
+        fn control_characters() {
+            let note = Note {
+                title: "\u{1b}[1m\u{85}",
+                text: "a\u{7}<b\u{2028}",
+            };
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer).with_version(XmlVersion::v1_1);
+            note.serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                "<?xml version=\"1.1\" encoding=\"UTF-8\"?>\
+                <Note title=\"&#x1B;[1m&#x85;\"><text>a&#x7;&lt;b&#x2028;</text></Note>"
+            );
+        }
+    }
+
+    mod invalid_chars {
+        use super::*;
+        use crate::ErrorKind;
+
+        #[derive(Serialize)]
+        struct Item {
+            #[serde(rename = "@id")]
+            id: &'static str,
+            name: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Order {
+            #[serde(rename = "item")]
+            items: Vec<Item>,
+        }
+
+        fn to_string(value: &Order, invalid_chars: InvalidChars) -> Result<String, Error> {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(false)
+                .with_invalid_chars(invalid_chars);
+            value.serialize(&mut ser)?;
+            Ok(String::from_utf8(buffer).unwrap())
+        }
+
+        fn order(id: &'static str, name: &'static str) -> Order {
+            Order {
+                items: vec![Item { id: "1", name: "a" }, Item { id, name }],
+            }
+        }
+
+        #[test]
+        fn error() {
+            let err = to_string(&order("2", "b\u{1b}"), InvalidChars::Error).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidChar);
+            assert_eq!(err.path(), Some("item/name"));
+            assert_eq!(
+                err.to_string(),
+                "Character U+001B is not allowed in XML in item/name"
+            );
+
+            let err = to_string(&order("\u{FFFF}", "b"), InvalidChars::Error).unwrap_err();
+            assert_eq!(err.path(), Some("item/@id"));
+        }
+
+        #[test]
+        fn replace() {
+            assert_eq!(
+                to_string(&order("\u{0}", "b\u{1b}"), InvalidChars::Replace('?')).unwrap(),
+                r#"<Order><item id="1"><name>a</name></item><item id="?"><name>b?</name></item></Order>"#
+            );
+            assert_eq!(
+                to_string(&order("\u{0}", "b\u{1b}"), InvalidChars::Remove).unwrap(),
+                r#"<Order><item id="1"><name>a</name></item><item id=""><name>b</name></item></Order>"#
+            );
+        }
+    }
+
+    mod invalid_names {
+        use std::collections::BTreeMap;
+
+        use super::*;
+        use crate::ErrorKind;
+
+        fn to_string(
+            map: &BTreeMap<&str, &str>,
+            invalid_names: InvalidNames,
+        ) -> Result<String, Error> {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new_with_root(&mut writer, Some("map"))
+                .with_xmldecl(false)
+                .with_invalid_names(invalid_names);
+            map.serialize(&mut ser)?;
+            Ok(String::from_utf8(buffer).unwrap())
+        }
+
+        fn metadata() -> BTreeMap<&'static str, &'static str> {
+            vec![("1st item", "a"), ("@x y", "b"), ("ok", "c")]
+                .into_iter()
+                .collect()
+        }
+
+        #[test]
+        fn error() {
+            let err = to_string(&metadata(), InvalidNames::Error).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidName);
+            assert_eq!(err.to_string(), "Invalid XML name `1st item`");
+        }
+
+        #[test]
+        fn sanitize() {
+            assert_eq!(
+                to_string(&metadata(), InvalidNames::Sanitize).unwrap(),
+                r#"<map x_y="b"><_1st_item>a</_1st_item><ok>c</ok></map>"#
+            );
+        }
+
+        #[test]
+        fn entry() {
+            assert_eq!(
+                to_string(&metadata(), InvalidNames::Entry).unwrap(),
+                r#"<map><entry key="1st item">a</entry><entry key="@x y">b</entry><ok>c</ok></map>"#
+            );
+
+            #[derive(Serialize)]
+            struct Value {
+                #[serde(rename = "@id")]
+                id: u32,
+                items: Vec<u32>,
+            }
+            let mut map = BTreeMap::new();
+            map.insert(
+                "a<b",
+                Value {
+                    id: 1,
+                    items: vec![2, 3],
+                },
+            );
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new_with_root(&mut writer, Some("map"))
+                .with_xmldecl(false)
+                .with_invalid_names(InvalidNames::Entry);
+            map.serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                r#"<map><entry key="a&lt;b" id="1"><items>2</items><items>3</items></entry></map>"#
+            );
+        }
+    }
+
+    mod child_elements {
+        use super::*;
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Step {
+            Checkout,
+            Build {
+                #[serde(rename = "@target")]
+                target: String,
+            },
+            Run(String),
+        }
+
+        #[derive(Serialize)]
+        struct Job {
+            #[serde(rename = "@id")]
+            id: u32,
+            name: String,
+            #[serde(rename = "$value")]
+            steps: Vec<Step>,
+            timeout: u32,
+        }
+
+        #[test]
+        fn between_fields() {
+            let job = Job {
+                id: 1,
+                name: "ci".into(),
+                steps: vec![
+                    Step::Checkout,
+                    Step::Build {
+                        target: "release".into(),
+                    },
+                    Step::Run("tests".into()),
+                ],
+                timeout: 60,
+            };
+            assert_eq!(
+                to_string_with_root(&job, "job").unwrap(),
+                r#"<job id="1"><name>ci</name><checkout/><build target="release"/><run>tests</run><timeout>60</timeout></job>"#
+            );
+        }
+
+        #[test]
+        fn flattened_struct_and_children() {
+            #[derive(Serialize)]
+            struct Meta {
+                author: String,
+            }
+
+            #[derive(Serialize)]
+            struct Job {
+                #[serde(rename = "@id")]
+                id: u32,
+                name: String,
+                #[serde(flatten)]
+                meta: Meta,
+                #[serde(flatten, with = "crate::children")]
+                steps: Vec<Step>,
+            }
+
+            let job = Job {
+                id: 1,
+                name: "ci".into(),
+                meta: Meta {
+                    author: "me".into(),
+                },
+                steps: vec![Step::Checkout, Step::Run("tests".into())],
+            };
+            assert_eq!(
+                to_string_with_root(&job, "job").unwrap(),
+                r#"<job id="1"><name>ci</name><author>me</author><checkout/><run>tests</run></job>"#
+            );
+        }
+
+        #[test]
+        fn flattened_with_map_entries() {
+            #[derive(Serialize)]
+            struct Owner {
+                name: String,
+            }
+
+            struct Filtered(BTreeMap<String, u32>);
+
+            impl Serialize for Filtered {
+                fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_map(self.0.iter().filter(|(_, count)| **count > 0))
+                }
+            }
+
+            #[derive(Serialize)]
+            struct Project {
+                #[serde(flatten)]
+                owner: Owner,
+                #[serde(with = "crate::entries")]
+                tags: BTreeMap<String, u32>,
+                #[serde(with = "crate::entries")]
+                filtered: Filtered,
+                counts: BTreeMap<String, u32>,
+            }
+
+            let mut tags = BTreeMap::new();
+            tags.insert("a b".to_string(), 1);
+            tags.insert("c".to_string(), 0);
+            let mut counts = BTreeMap::new();
+            counts.insert("c".to_string(), 2);
+            let project = Project {
+                owner: Owner { name: "Ann".into() },
+                tags: tags.clone(),
+                filtered: Filtered(tags),
+                counts,
+            };
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new_with_root(&mut writer, Some("project"))
+                .with_xmldecl(false)
+                .with_entry_names("tag", "name");
+            project.serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                concat!(
+                    r#"<project><name>Ann</name>"#,
+                    r#"<tags><tag name="a b">1</tag><tag name="c">0</tag></tags>"#,
+                    r#"<filtered><tag name="a b">1</tag></filtered>"#,
+                    r#"<counts><c>2</c></counts></project>"#
+                )
+            );
+        }
+    }
+
+    mod mixed_content {
+        use super::*;
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Inline {
+            #[serde(rename = "$text")]
+            Text(String),
+            B(String),
+            A {
+                #[serde(rename = "@href")]
+                href: String,
+                #[serde(rename = "$value")]
+                content: Vec<Inline>,
+            },
+            Br,
+        }
+
+        #[derive(Serialize)]
+        struct Paragraph {
+            #[serde(rename = "$value")]
+            content: Vec<Inline>,
+        }
+
+        #[test]
+        fn text_and_elements() {
+            let paragraph = Paragraph {
+                content: vec![
+                    Inline::Text("Hello ".into()),
+                    Inline::B("world".into()),
+                    Inline::Text("!".into()),
+                    Inline::Br,
+                    Inline::A {
+                        href: "x".into(),
+                        content: vec![Inline::Text("a < b".into())],
+                    },
+                ],
+            };
+            assert_eq!(
+                to_string_with_root(&paragraph, "p").unwrap(),
+                r#"<p>Hello <b>world</b>!<br/><a href="x">a &lt; b</a></p>"#
+            );
+        }
+
+        #[test]
+        fn nested_inline() {
+            #[derive(Serialize)]
+            #[serde(rename_all = "lowercase")]
+            enum Nested {
+                #[serde(rename = "$text")]
+                Text(String),
+                B(String),
+                I(Vec<Nested>),
+            }
+
+            #[derive(Serialize)]
+            struct Paragraph {
+                #[serde(rename = "$value")]
+                content: Vec<Nested>,
+            }
+
+            let paragraph = Paragraph {
+                content: vec![
+                    Nested::I(vec![
+                        Nested::Text("x ".into()),
+                        Nested::B("y".into()),
+                        Nested::I(vec![Nested::Text("z".into())]),
+                    ]),
+                    Nested::Text(" end".into()),
+                ],
+            };
+            assert_eq!(
+                to_string_with_root(&paragraph, "p").unwrap(),
+                r#"<p><i>x <b>y</b><i>z</i></i> end</p>"#
+            );
+        }
+
+        #[test]
+        fn field() {
+            #[derive(Serialize)]
+            struct Item {
+                title: Inline,
+            }
+
+            let item = Item {
+                title: Inline::Text("text".into()),
+            };
+            assert_eq!(
+                to_string_with_root(&item, "item").unwrap(),
+                r#"<item><title>text</title></item>"#
+            );
+        }
+    }
+
+    mod booleans {
+        use super::*;
+        use crate::element::Element;
+
+        #[derive(Serialize)]
+        struct Account {
+            #[serde(rename = "@active")]
+            active: bool,
+            locked: bool,
+        }
+
+        fn to_string_with_booleans<T: Serialize>(value: &T, booleans: Booleans) -> String {
+            let mut buffer = Vec::new();
+            let mut writer = Writer::new(&mut buffer);
+            let mut ser = Serializer::new(&mut writer)
+                .with_xmldecl(false)
+                .with_booleans(booleans);
+            value.serialize(&mut ser).unwrap();
+            String::from_utf8(buffer).unwrap()
+        }
+
+        #[test]
+        fn custom() {
+            let account = Account {
+                active: true,
+                locked: false,
+            };
+            assert_eq!(
+                to_string_with_booleans(&account, Booleans::custom("Y", "N")),
+                r#"<Account active="Y"><locked>N</locked></Account>"#
+            );
+            assert_eq!(
+                to_string_with_booleans(&account, Booleans::custom("1", "0")),
+                r#"<Account active="1"><locked>0</locked></Account>"#
+            );
+            assert_eq!(
+                to_string_with_booleans(&account, Booleans::custom("<on>", "off")),
+                r#"<Account active="&lt;on&gt;"><locked>off</locked></Account>"#
+            );
+        }
+
+        #[test]
+        fn default() {
+            let account = Account {
+                active: true,
+                locked: false,
+            };
+            assert_eq!(
+                to_string_with_booleans(&account, Booleans::Strict),
+                r#"<Account active="true"><locked>false</locked></Account>"#
+            );
+        }
+
+        #[test]
+        fn element() {
+            use serde::de::{value, Deserialize, IntoDeserializer};
+
+            let deserializer: value::BoolDeserializer<value::Error> = true.into_deserializer();
+            let element = Element::deserialize(deserializer).unwrap();
+            assert_eq!(element.text(), "true");
+        }
     }
 }
@@ -0,0 +1,119 @@
+//! Incremental writing of large elements
+
+use std::io::Write;
+
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use serde::ser::Serialize;
+
+use crate::error::Reason;
+use crate::ser::attributes::AttributeSerializer;
+use crate::ser::Serializer;
+use crate::Error;
+
+/// An element, whose children are written one at a time, see [`Serializer::stream_element`]
+///
+/// The start tag is written with the first child. Attributes can only be added before.
+pub struct ElementStream<'s, 'r, 'a, W: Write> {
+    parent: &'s mut Serializer<'r, 'a, W>,
+    /// Start tag, until it is written
+    start: Option<BytesStart<'s>>,
+    /// Resolved name of the element
+    name: Vec<u8>,
+}
+
+impl<'s, 'r, 'a, W: Write> ElementStream<'s, 'r, 'a, W> {
+    pub(crate) fn new(parent: &'s mut Serializer<'r, 'a, W>, name: &'s str) -> Result<Self, Error> {
+        let start = parent.start_tag(name)?;
+        Ok(ElementStream {
+            name: start.name().to_vec(),
+            start: Some(start),
+            parent,
+        })
+    }
+
+    /// Add an attribute to the start tag
+    pub fn with_attribute<T: ?Sized + Serialize>(
+        mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<Self, Error> {
+        let name = self.parent.resolve_name(name, true)?;
+        let start = match self.start.as_mut() {
+            Some(start) => start,
+            None => {
+                return Err(self.parent.error(Reason::Message(
+                    "attributes must be added before the first child".to_string(),
+                )))
+            }
+        };
+        if let Some(value) =
+            value.serialize(&mut AttributeSerializer::new(&self.parent.settings))?
+        {
+            start.push_attribute(self.parent.attribute(&name, &value)?);
+        }
+        Ok(self)
+    }
+
+    /// Write a child, which gets its own name like a root element
+    pub fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.write_child(None, value)
+    }
+
+    /// Write a child as element `name`
+    pub fn push_element<T: ?Sized + Serialize>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.write_child(Some(name), value)
+    }
+
+    /// Write the end tag
+    pub fn end(mut self) -> Result<(), Error> {
+        match self.start.take() {
+            Some(start) => {
+                self.parent.write_declaration()?;
+                self.parent.write_indent(false)?;
+                self.parent.write_event(Event::Empty(start))?;
+            }
+            None => {
+                self.parent.write_indent(true)?;
+                self.parent
+                    .write_event(Event::End(BytesEnd::borrowed(&self.name)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_child<T: ?Sized + Serialize>(
+        &mut self,
+        name: Option<&str>,
+        value: &T,
+    ) -> Result<(), Error> {
+        if let Some(start) = self.start.take() {
+            self.parent.write_declaration()?;
+            self.parent.write_indent(false)?;
+            self.parent.write_event(Event::Start(start))?;
+        }
+
+        let root_tag = match name {
+            Some(name) => Some(self.parent.resolve_name(name, false)?),
+            None => None,
+        };
+        let mut serializer = Serializer {
+            writer: &mut *self.parent.writer,
+            root_tag: root_tag.as_deref(),
+            version: self.parent.version,
+            fragment: true,
+            settings: self.parent.settings.clone(),
+            top_level: false,
+            depth: self.parent.depth + 1,
+            encode_output: self.parent.encode_output,
+            root_attribute: None,
+            has_text: None,
+            map_entries: false,
+            variant_content: false,
+        };
+        value.serialize(&mut serializer)
+    }
+}
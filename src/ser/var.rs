@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::io::Write;
 
 use quick_xml::{
@@ -6,11 +7,13 @@ use quick_xml::{
 };
 use serde::ser::{self, Serialize};
 
+use crate::chars::{is_name, sanitize_name};
+use crate::de::TAG_NAME;
+use crate::entries::{ENTRY, ENTRY_KEY};
 use crate::error::Reason;
 use crate::ser::attributes::AttributeSerializer;
-use crate::ser::Serializer;
-use crate::Error;
-use quick_xml::events::BytesDecl;
+use crate::ser::{Serializer, INDENT_MARK};
+use crate::{Error, InvalidNames};
 
 /// An implementation of `SerializeStruct` for serializing to XML.
 pub struct Struct<'r, 'w, 'a, W>
@@ -25,6 +28,10 @@ where
     children: Vec<u8>,
     /// Buffer for serializing one field. Cleared after serialize each field
     buffer: Vec<u8>,
+    /// Map written as `<entry key="k">value</entry>` elements
+    entries: bool,
+    /// Text was written as content, so children are not indented
+    has_text: Cell<bool>,
 }
 
 impl<'r, 'a, 'w, W> Struct<'r, 'w, 'a, W>
@@ -32,69 +39,180 @@ where
     W: 'w + Write,
 {
     /// Create a new `Struct`
-    pub fn new(parent: &'w mut Serializer<'r, 'a, W>, name: &'w str) -> Self {
-        let name = name.as_bytes();
-        Struct {
+    pub fn new(parent: &'w mut Serializer<'r, 'a, W>, name: &'w str) -> Result<Self, Error> {
+        let attrs = parent.start_tag(name)?;
+        Ok(Struct {
             parent,
-            attrs: BytesStart::borrowed_name(name),
+            attrs,
             children: Vec::new(),
             buffer: Vec::new(),
-        }
+            entries: false,
+            has_text: Cell::new(false),
+        })
     }
 
-    fn serialize_tag<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
-        // TODO: Inherit indentation state from self.parent.writer
+    /// Create a new `Struct` for a map, which is optionally written as entries
+    pub fn new_map(
+        parent: &'w mut Serializer<'r, 'a, W>,
+        name: &'w str,
+        entries: bool,
+    ) -> Result<Self, Error> {
+        let mut map = Self::new(parent, name)?;
+        map.entries = entries;
+        Ok(map)
+    }
 
-        if let Some(key) = key.strip_prefix('@') {
+    fn serialize_tag<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        if key == TAG_NAME {
+            // a tag name given by the parent takes precedence
+            if self.attrs.name().is_empty() {
+                let mut serializer = AttributeSerializer::new(&self.parent.settings);
+                if let Some(tag) = value.serialize(&mut serializer)? {
+                    let tag = self.parent.resolve_name(&tag, false)?;
+                    self.attrs.set_name(tag.as_bytes());
+                }
+            }
+        } else if let Some(key) = key.strip_prefix('@') {
             if key.is_empty() {
                 return Err(self
                     .parent
                     .error(Reason::Message("name for attribute is missing".to_string())));
             }
 
-            let mut serializer = AttributeSerializer::new();
+            let key = self.parent.resolve_name(key, true)?;
+            let mut serializer = AttributeSerializer::new(&self.parent.settings);
             let attribute_value = value.serialize(&mut serializer)?;
             if let Some(attribute_value) = attribute_value {
-                self.attrs.push_attribute((key, &attribute_value as &str));
+                let attribute = self
+                    .parent
+                    .attribute(&key, &attribute_value)
+                    .map_err(|err| err.within(&format!("@{}", key)))?;
+                self.attrs.push_attribute(attribute);
             }
             self.buffer.clear();
         } else {
             let root = if key.starts_with('$') {
                 None
             } else {
-                Some(key)
+                Some(self.parent.resolve_name(key, false)?)
             };
             let mut writer = Writer::new(&mut self.buffer);
-            let mut serializer = Serializer::new_with_root(&mut writer, root).with_xmldecl(false);
-            value.serialize(&mut serializer)?;
+            let mut serializer = self.parent.nested(&mut writer, root.as_deref());
+            if root.is_none() {
+                serializer.has_text = Some(&self.has_text);
+            }
+            let result = value.serialize(&mut serializer);
+            match root {
+                Some(root) => result.map_err(|err| err.within(&root))?,
+                None => result?,
+            }
 
             self.children.append(&mut self.buffer);
         }
         Ok(())
     }
 
+    /// Serializes a map entry, handling keys, which are no valid names
+    fn serialize_key_value<T: ?Sized + Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        if self.entries {
+            return self.serialize_entry_element(key, value);
+        }
+
+        // names with a namespace URI are checked by their local name
+        let (prefix, name) = match key.find('}') {
+            Some(end) if key.starts_with('{') => key.split_at(end + 1),
+            _ => match key.strip_prefix('@') {
+                Some(name) => ("@", name),
+                None => ("", key),
+            },
+        };
+        if key.starts_with('$') || is_name(name) {
+            return self.serialize_tag(key, value);
+        }
+
+        match self.parent.settings.invalid_names {
+            InvalidNames::Keep => self.serialize_tag(key, value),
+            InvalidNames::Error => Err(self.parent.error(Reason::InvalidName(key.to_string()))),
+            InvalidNames::Sanitize => {
+                self.serialize_tag(&format!("{}{}", prefix, sanitize_name(name)), value)
+            }
+            InvalidNames::Entry => self.serialize_entry_element(key, value),
+        }
+    }
+
+    /// Serializes a map entry as `<entry key="key">value</entry>`
+    fn serialize_entry_element<T: ?Sized + Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let settings = self.parent.settings.clone();
+        let (element, attribute) = match &settings.entry_names {
+            Some((element, attribute)) => (element.as_str(), attribute.as_str()),
+            None => (ENTRY, ENTRY_KEY),
+        };
+        let mut writer = Writer::new(&mut self.buffer);
+        let mut serializer = self.parent.nested(&mut writer, Some(element));
+        serializer.root_attribute = Some((attribute, key));
+        value
+            .serialize(&mut serializer)
+            .map_err(|err| err.within(element))?;
+        self.children.append(&mut self.buffer);
+        Ok(())
+    }
+
     fn close(&mut self) -> Result<(), Error> {
-        let writer = &mut self.parent.writer;
-
-        if !self.parent.fragment {
-            writer.write_event(Event::Decl(BytesDecl::new(
-                self.parent.version.as_bytes(),
-                Some(b"UTF-8"), // TODO: make it configurable
-                None,
-            )))?;
+        if self.attrs.name().is_empty() {
+            return Err(self
+                .parent
+                .error(Reason::Message("tag name for map is missing".to_string())));
         }
 
+        self.parent.write_declaration()?;
+        self.parent.write_indent(false)?;
+
         if self.children.is_empty() {
-            writer.write_event(Event::Empty(self.attrs.to_borrowed()))?;
+            self.parent
+                .write_event(Event::Empty(self.attrs.to_borrowed()))?;
         } else {
-            writer.write_event(Event::Start(self.attrs.to_borrowed()))?;
-            writer.write(&self.children)?;
-            writer.write_event(Event::End(self.attrs.to_end()))?;
+            self.parent
+                .write_event(Event::Start(self.attrs.to_borrowed()))?;
+            // whitespace in mixed content would change the text
+            let has_text = self.has_text.get();
+            if has_text || self.parent.encode_output {
+                self.children = resolve_indentation(&self.children, has_text);
+            }
+            self.parent.write_raw(&self.children)?;
+            if !has_text {
+                self.parent.write_indent(true)?;
+            }
+            self.parent.write_event(Event::End(self.attrs.to_end()))?;
         }
         Ok(())
     }
 }
 
+/// Removes the marks of indentation from `xml`, and with `remove` also the indentation
+fn resolve_indentation(xml: &[u8], remove: bool) -> Vec<u8> {
+    let mut result = Vec::with_capacity(xml.len());
+    let mut parts = xml.split(|&b| b == INDENT_MARK);
+    result.extend_from_slice(parts.next().unwrap_or_default());
+    for part in parts {
+        // indentation always precedes a tag
+        let start = if remove {
+            part.iter().position(|&b| b == b'<').unwrap_or(part.len())
+        } else {
+            0
+        };
+        result.extend_from_slice(&part[start..]);
+    }
+    result
+}
+
 impl<'r, 'w, 'a, W> ser::SerializeStruct for Struct<'r, 'w, 'a, W>
 where
     W: 'w + Write,
@@ -166,9 +284,9 @@ where
         value: &V,
     ) -> Result<(), Error> {
         // TODO: use own TagSerializer
-        let tag = key.serialize(&mut AttributeSerializer::new())?;
+        let tag = key.serialize(&mut AttributeSerializer::new(&self.parent.settings))?;
         if let Some(tag) = tag {
-            self.serialize_tag(&tag, value)
+            self.serialize_key_value(&tag, value)
         } else {
             Err(self.parent.error(Reason::Message(
                 "Option as map key not supported".to_string(),
@@ -188,6 +306,8 @@ where
     W: Write,
 {
     parent: &'w mut Serializer<'r, 'a, W>,
+    /// Element around the items, which is closed at the end
+    end_tag: Option<&'r str>,
 }
 
 impl<'r, 'w, 'a, W> Seq<'r, 'w, 'a, W>
@@ -196,7 +316,18 @@ where
 {
     /// Create a new `Tuple`
     pub fn new(parent: &'w mut Serializer<'r, 'a, W>) -> Self {
-        Seq { parent }
+        Seq {
+            parent,
+            end_tag: None,
+        }
+    }
+
+    /// Create a new `Seq` in the started element `tag`
+    pub fn with_end(parent: &'w mut Serializer<'r, 'a, W>, tag: &'r str) -> Self {
+        Seq {
+            parent,
+            end_tag: Some(tag),
+        }
     }
 
     fn serialize_item<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
@@ -222,6 +353,9 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(tag) = self.end_tag {
+            self.parent.write_tag_end(tag)?;
+        }
         Ok(())
     }
 }